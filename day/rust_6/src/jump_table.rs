@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+
+use crate::{Coord, Direction, Grid, PatrolResult, TileKind};
+
+// Where a guard walking in a straight line comes to a stop: the tile in front
+// of an obstacle, or the last tile before it walks off the edge of the map.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Stop {
+    Obstacle(usize),
+    Edge(usize),
+}
+
+// Precomputed stops for every tile and direction, letting the guard cover a
+// whole straight segment in one jump. A single extra obstacle can be placed
+// on top of the table without rebuilding it.
+#[derive(Debug, Clone)]
+pub struct JumpTable {
+    stops: Vec<[Stop; 4]>,
+    row_len: usize,
+    extra_obstacle: Option<Coord>,
+}
+
+fn direction_index(direction: &Direction) -> usize {
+    match direction {
+        Direction::Up => 0,
+        Direction::Right => 1,
+        Direction::Down => 2,
+        Direction::Left => 3,
+    }
+}
+
+impl JumpTable {
+    pub fn new(grid: &Grid) -> Self {
+        let row_len = grid.row_len;
+        let col_len = grid.col_len();
+        let mut table = Self {
+            stops: (0..grid.tiles.len()).map(|i| [Stop::Edge(i); 4]).collect(),
+            row_len,
+            extra_obstacle: None,
+        };
+
+        for x in 0..row_len {
+            let column: Vec<usize> = (0..col_len).map(|y| y * row_len + x).collect();
+
+            table.fill_line(grid, column.iter().copied(), &Direction::Up);
+            table.fill_line(grid, column.iter().copied().rev(), &Direction::Down);
+        }
+
+        for y in 0..col_len {
+            let row: Vec<usize> = (0..row_len).map(|x| y * row_len + x).collect();
+
+            table.fill_line(grid, row.iter().copied(), &Direction::Left);
+            table.fill_line(grid, row.iter().copied().rev(), &Direction::Right);
+        }
+
+        table
+    }
+
+    // Sweeps a row or column starting from the edge the guard walks towards.
+    fn fill_line(&mut self, grid: &Grid, line: impl Iterator<Item = usize>, direction: &Direction) {
+        let d = direction_index(direction);
+        let mut stop: Option<Stop> = None;
+        let mut at_edge = true;
+
+        for index in line {
+            if grid.tiles[index].kind == TileKind::Obstacle {
+                stop = None;
+                at_edge = false;
+                continue;
+            }
+
+            self.stops[index][d] = *stop.get_or_insert(if at_edge {
+                Stop::Edge(index)
+            } else {
+                Stop::Obstacle(index)
+            });
+        }
+    }
+
+    pub fn place_obstacle(&mut self, coord: &Coord) {
+        self.extra_obstacle = Some(coord.clone());
+    }
+
+    pub fn remove_obstacle(&mut self) {
+        self.extra_obstacle = None;
+    }
+
+    fn index_to_coord(&self, index: usize) -> Coord {
+        Coord {
+            x: index % self.row_len,
+            y: index / self.row_len,
+        }
+    }
+
+    pub fn jump(&self, from: &Coord, direction: &Direction) -> Stop {
+        let from_index = from.y * self.row_len + from.x;
+        let stop = self.stops[from_index][direction_index(direction)];
+
+        let Some(extra) = &self.extra_obstacle else {
+            return stop;
+        };
+
+        let end = match stop {
+            Stop::Obstacle(index) | Stop::Edge(index) => self.index_to_coord(index),
+        };
+
+        let (x, y) = match direction {
+            Direction::Up if extra.x == from.x && extra.y < from.y && extra.y >= end.y => {
+                (extra.x, extra.y + 1)
+            },
+            Direction::Right if extra.y == from.y && extra.x > from.x && extra.x <= end.x => {
+                (extra.x - 1, extra.y)
+            },
+            Direction::Down if extra.x == from.x && extra.y > from.y && extra.y <= end.y => {
+                (extra.x, extra.y - 1)
+            },
+            Direction::Left if extra.y == from.y && extra.x < from.x && extra.x >= end.x => {
+                (extra.x + 1, extra.y)
+            },
+            _ => return stop,
+        };
+
+        Stop::Obstacle(y * self.row_len + x)
+    }
+
+    // Jumps the guard from obstacle to obstacle until it leaves the map or
+    // arrives in front of the same obstacle twice, facing the same way.
+    pub fn patrol(&self, start: &Coord, direction: &Direction) -> PatrolResult {
+        let mut coord = start.clone();
+        let mut direction = direction.clone();
+        let mut turns: HashSet<(usize, Direction)> = HashSet::new();
+
+        loop {
+            match self.jump(&coord, &direction) {
+                Stop::Edge(_) => return PatrolResult::Exited,
+                Stop::Obstacle(index) => {
+                    coord = self.index_to_coord(index);
+
+                    if !turns.insert((index, direction.clone())) {
+                        return PatrolResult::Looped(coord);
+                    }
+
+                    direction = Direction::turn_right(&direction);
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn fixture() -> Grid {
+        Grid::from_str(&crate::tests::fixture()).unwrap()
+    }
+
+    #[test]
+    fn jumps_to_the_tile_in_front_of_the_next_obstacle() {
+        let table = JumpTable::new(&fixture());
+
+        assert_eq!(table.jump(&Coord { x: 4, y: 6 }, &Direction::Up), Stop::Obstacle(14));
+        assert_eq!(table.jump(&Coord { x: 4, y: 1 }, &Direction::Right), Stop::Obstacle(18));
+        assert_eq!(table.jump(&Coord { x: 4, y: 6 }, &Direction::Left), Stop::Obstacle(62));
+        assert_eq!(table.jump(&Coord { x: 7, y: 7 }, &Direction::Down), Stop::Edge(97));
+    }
+
+    #[test]
+    fn stops_in_front_of_a_placed_obstacle() {
+        let mut table = JumpTable::new(&fixture());
+
+        table.place_obstacle(&Coord { x: 4, y: 3 });
+
+        assert_eq!(table.jump(&Coord { x: 4, y: 6 }, &Direction::Up), Stop::Obstacle(44));
+        assert_eq!(table.jump(&Coord { x: 4, y: 2 }, &Direction::Up), Stop::Obstacle(14));
+
+        table.remove_obstacle();
+
+        assert_eq!(table.jump(&Coord { x: 4, y: 6 }, &Direction::Up), Stop::Obstacle(14));
+    }
+
+    #[test]
+    fn patrols_to_the_same_outcome_as_the_grid() {
+        let mut table = JumpTable::new(&fixture());
+        let start = Coord { x: 4, y: 6 };
+
+        assert_eq!(table.patrol(&start, &Direction::Up), PatrolResult::Exited);
+
+        table.place_obstacle(&Coord { x: 3, y: 6 });
+
+        assert!(matches!(table.patrol(&start, &Direction::Up), PatrolResult::Looped(_)));
+    }
+}
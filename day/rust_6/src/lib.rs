@@ -0,0 +1,632 @@
+use core::fmt;
+use std::{collections::HashSet, mem, str::FromStr};
+
+mod jump_table;
+
+pub use jump_table::{JumpTable, Stop};
+
+#[derive(Debug, PartialEq, Hash, Eq, Clone)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left
+}
+
+impl Direction {
+    pub fn turn_right(direction: &Direction) -> Direction {
+        match direction {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TileKind {
+    Empty,
+    Obstacle,
+    Guard(Direction),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseTileKindError;
+
+impl TileKind {
+    pub fn from_char(c: &char) -> Result<Self, ParseTileKindError> {
+        match c {
+            '.' => Ok(TileKind::Empty),
+            '#' => Ok(TileKind::Obstacle),
+            '^' => Ok(TileKind::Guard(Direction::Up)),
+            '>' => Ok(TileKind::Guard(Direction::Right)),
+            'v' => Ok(TileKind::Guard(Direction::Down)),
+            '<' => Ok(TileKind::Guard(Direction::Left)),
+            _ => Err(ParseTileKindError)
+        }
+    }
+    pub fn is_guard(&self) -> bool {
+        matches!(self, TileKind::Guard(_))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Coord {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(x:{},y:{})", self.x, self.y)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GridTile {
+    pub kind: TileKind,
+    pub visited: HashSet<Direction>,
+}
+
+impl GridTile {
+    pub fn new(kind: TileKind) -> Self {
+        let visited = match &kind {
+            TileKind::Guard(direction) => HashSet::from([direction.clone()]),
+            _ => HashSet::new(),
+        };
+
+        Self {
+            kind,
+            visited,
+        }
+    }
+
+    // Returns false if the tile had already been visited in this direction.
+    pub fn set_visited(&mut self, direction: Direction) -> bool {
+        self.visited.insert(direction)
+    }
+
+    pub fn get_visited(&self) -> bool {
+        !self.visited.is_empty()
+    }
+
+    // Returns false if the guard has faced the new direction on this tile before.
+    pub fn turn_right(&mut self) -> bool {
+        if let TileKind::Guard(direction) = &self.kind {
+            let new_direction = Direction::turn_right(direction);
+            self.kind = TileKind::Guard(new_direction.clone());
+            return self.set_visited(new_direction);
+        };
+
+        true
+    }
+}
+
+impl fmt::Display for GridTile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TileKind::Empty => {
+                let v = &self.visited;
+
+                let up_or_down = v.contains(&Direction::Up) || v.contains(&Direction::Down);
+                let left_or_right = v.contains(&Direction::Left) || v.contains(&Direction::Right);
+
+                if up_or_down && left_or_right {
+                    write!(f, "+")
+                } else if up_or_down {
+                    write!(f, "|")
+                } else if left_or_right {
+                    write!(f, "-")
+                } else {
+                    write!(f, ".")
+                }
+            },
+            TileKind::Obstacle => write!(f, "#"),
+            TileKind::Guard(Direction::Up) => write!(f, "^"),
+            TileKind::Guard(Direction::Right) => write!(f, ">"),
+            TileKind::Guard(Direction::Down) => write!(f, "v"),
+            TileKind::Guard(Direction::Left) => write!(f, "<"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Grid {
+    pub tiles: Vec<GridTile>,
+    pub row_len: usize,
+    pub guard: Option<Coord>,
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut to_write = String::new();
+
+        for row in self.tiles.chunks(self.row_len) {
+            for cell in row {
+                to_write.push_str(&cell.to_string());
+            }
+            to_write.push('\n');
+        }
+
+        write!(f, "{}", to_write)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MovementResult {
+    Ok,
+    TileIsNotGuard,
+    NoNeighbor,
+    Obstructed,
+    Looped(Coord),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PatrolResult {
+    Exited,
+    Looped(Coord),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WalkEvent {
+    Moved(Coord, Direction),
+    Turned(Direction),
+    Exited(Coord),
+    Looped(Coord),
+}
+
+// Steps the guard through its walk one event at a time. The walk ends after the
+// guard leaves the map or loops.
+pub struct Walk<'a> {
+    grid: &'a mut Grid,
+    finished: bool,
+}
+
+impl Iterator for Walk<'_> {
+    type Item = WalkEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let guard = self.grid.guard.clone()?;
+        let direction = self.grid.guard_direction()?;
+
+        let event = match self.grid.move_tile(&guard) {
+            MovementResult::Ok => WalkEvent::Moved(self.grid.guard.clone()?, direction),
+            MovementResult::Obstructed => {
+                let index = self.grid.coord_to_index(&guard);
+
+                if self.grid.tiles[index].turn_right() {
+                    WalkEvent::Turned(self.grid.guard_direction()?)
+                } else {
+                    WalkEvent::Looped(guard)
+                }
+            },
+            MovementResult::Looped(coord) => WalkEvent::Looped(coord),
+            MovementResult::NoNeighbor => WalkEvent::Exited(guard),
+            MovementResult::TileIsNotGuard => return None,
+        };
+
+        if let WalkEvent::Exited(_) | WalkEvent::Looped(_) = event {
+            self.finished = true;
+        }
+
+        Some(event)
+    }
+}
+
+enum NeighborTile<T> {
+    Ok(T),
+    NoNeighbor,
+    Obstructed,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseGridError;
+
+impl FromStr for Grid {
+    type Err = ParseGridError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tiles = Vec::new();
+        let mut row_len = 0;
+        let mut guard = None;
+
+        for (y, line) in s.trim_end().lines().enumerate() {
+            if y == 0 {
+                row_len = line.len();
+            }
+            // Every row has to be as long as the first, or neighbours can't be
+            // found by index.
+            if line.len() != row_len {
+                return Err(ParseGridError);
+            }
+            for (x, content) in line.chars().enumerate() {
+                match TileKind::from_char(&content) {
+                    Ok(kind) => {
+                        if kind.is_guard() {
+                            guard = Some(Coord { x, y });
+                        }
+                        tiles.push(GridTile::new(kind));
+                    },
+                    Err(_) => return Err(ParseGridError),
+                }
+            }
+        }
+
+        if tiles.is_empty() {
+            return Err(ParseGridError);
+        }
+
+        Ok(Self {
+            tiles,
+            row_len,
+            guard,
+        })
+    }
+}
+
+impl Grid {
+    pub fn get_guard(&self) -> Option<&GridTile> {
+        self.guard.as_ref().map(|coord| &self.tiles[self.coord_to_index(coord)])
+    }
+
+    pub fn guard_direction(&self) -> Option<Direction> {
+        match &self.get_guard()?.kind {
+            TileKind::Guard(direction) => Some(direction.clone()),
+            _ => None,
+        }
+    }
+
+    fn col_len(&self) -> usize {
+        self.tiles.len() / self.row_len
+    }
+
+    fn get_neighbor_coord(&self, coord: &Coord, direction: &Direction) -> Option<Coord> {
+        match direction {
+            Direction::Up if coord.y == 0 => None,
+            Direction::Right if coord.x == self.row_len - 1 => None,
+            Direction::Down if coord.y == self.col_len() - 1 => None,
+            Direction::Left if coord.x == 0 => None,
+            Direction::Up => Some(Coord { x: coord.x, y: coord.y - 1 }),
+            Direction::Right => Some(Coord { x: coord.x + 1, y: coord.y }),
+            Direction::Down => Some(Coord { x: coord.x, y: coord.y + 1 }),
+            Direction::Left => Some(Coord { x: coord.x - 1, y: coord.y }),
+        }
+    }
+
+    fn get_unobstructed_neighbor(&self, coord: &Coord, direction: &Direction) -> NeighborTile<usize> {
+        if let Some(neighbor_coord) = self.get_neighbor_coord(coord, direction) {
+            let tile_index = self.coord_to_index(&neighbor_coord);
+
+            if self.tiles[tile_index].kind == TileKind::Obstacle {
+                NeighborTile::Obstructed
+            } else {
+                NeighborTile::Ok(tile_index)
+            }
+        } else {
+            NeighborTile::NoNeighbor
+        }
+    }
+
+    fn coord_to_index(&self, coord: &Coord) -> usize {
+        coord.y * self.row_len + coord.x
+    }
+
+    fn index_to_coord(&self, index: usize) -> Coord {
+        Coord {
+            x: index % self.row_len,
+            y: index / self.row_len
+        }
+    }
+
+    pub fn visited_coords(&self) -> Vec<Coord> {
+        self.tiles.iter()
+            .enumerate()
+            .filter(|(_, t)| t.get_visited())
+            .map(|(i, _)| self.index_to_coord(i))
+            .collect()
+    }
+
+    pub fn place_obstacle(&mut self, coord: &Coord) {
+        let index = self.coord_to_index(coord);
+
+        self.tiles[index].kind = TileKind::Obstacle;
+    }
+
+    pub fn move_tile(&mut self, coord: &Coord) -> MovementResult {
+        let index = self.coord_to_index(coord);
+
+        let direction = match &self.tiles[index].kind {
+            TileKind::Guard(direction) => direction.clone(),
+            _ => return MovementResult::TileIsNotGuard,
+        };
+
+        match self.get_unobstructed_neighbor(coord, &direction) {
+            NeighborTile::Ok(neighbor_index) => {
+                let first_visit = self.tiles[neighbor_index].set_visited(direction);
+                let kind = mem::replace(&mut self.tiles[index].kind, TileKind::Empty);
+                let neighbor_coord = self.index_to_coord(neighbor_index);
+
+                self.tiles[neighbor_index].kind = kind;
+                self.guard = Some(neighbor_coord.clone());
+
+                if first_visit {
+                    MovementResult::Ok
+                } else {
+                    MovementResult::Looped(neighbor_coord)
+                }
+            }
+            NeighborTile::NoNeighbor => MovementResult::NoNeighbor,
+            NeighborTile::Obstructed => MovementResult::Obstructed,
+        }
+    }
+
+    pub fn walk(&mut self) -> Walk<'_> {
+        Walk {
+            grid: self,
+            finished: false,
+        }
+    }
+
+    // Walks the guard until it either leaves the map or steps back onto a
+    // tile in a direction it has already travelled there.
+    pub fn patrol(&mut self) -> PatrolResult {
+        match self.walk().last() {
+            Some(WalkEvent::Looped(coord)) => PatrolResult::Looped(coord),
+            _ => PatrolResult::Exited,
+        }
+    }
+
+    // Tries an extra obstacle on every tile of the guard's original route (other
+    // than where it starts) and returns the coords of those which trap it in a loop.
+    // Finds nothing when there is no guard to trap.
+    pub fn find_looping_obstructions(&self) -> Vec<Coord> {
+        let Some(start) = self.guard.clone() else {
+            return Vec::new();
+        };
+        let mut route = self.clone();
+
+        route.patrol();
+
+        route.visited_coords()
+            .into_iter()
+            .filter(|coord| *coord != start)
+            .filter(|coord| {
+                let mut candidate = self.clone();
+
+                candidate.place_obstacle(coord);
+
+                matches!(candidate.patrol(), PatrolResult::Looped(_))
+            })
+            .collect()
+    }
+
+    // Same as find_looping_obstructions, but each candidate is simulated on a
+    // JumpTable rather than by walking a fresh copy of the grid.
+    pub fn find_looping_obstructions_fast(&self) -> Vec<Coord> {
+        let (Some(start), Some(direction)) = (self.guard.clone(), self.guard_direction()) else {
+            return Vec::new();
+        };
+        let mut table = JumpTable::new(self);
+        let mut route = self.clone();
+
+        route.patrol();
+
+        route.visited_coords()
+            .into_iter()
+            .filter(|coord| *coord != start)
+            .filter(|coord| {
+                table.place_obstacle(coord);
+
+                let looped = matches!(table.patrol(&start, &direction), PatrolResult::Looped(_));
+
+                table.remove_obstacle();
+                looped
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Strips the indentation from a map written inside a test.
+    fn unindent(map: &str) -> String {
+        map.lines().map(|l| format!("{}\n", l.trim())).collect()
+    }
+
+    pub(crate) fn fixture() -> String {
+        unindent("....#.....
+         .........#
+         ..........
+         ..#.......
+         .......#..
+         ..........
+         .#..^.....
+         ........#.
+         #.........
+         ......#...")
+    }
+
+    #[test]
+    fn maintains_its_string_representation() {
+        let grid = Grid::from_str(&fixture()).unwrap();
+
+        assert_eq!(grid.to_string(), fixture());
+    }
+
+    #[test]
+    fn exits_the_map_when_there_is_no_loop() {
+        let mut grid = Grid::from_str(&fixture()).unwrap();
+
+        assert_eq!(grid.patrol(), PatrolResult::Exited);
+
+        let visited = grid.tiles.iter()
+            .filter(|t| t.get_visited())
+            .count();
+
+        assert_eq!(visited, 41);
+    }
+
+    #[test]
+    fn renders_the_route_taken_by_the_guard() {
+        let mut grid = Grid::from_str(&fixture()).unwrap();
+
+        grid.patrol();
+
+        assert_eq!(
+            grid.to_string(),
+            "....#.....
+             ....+---+#
+             ....|...|.
+             ..#.|...|.
+             ..+-+-+#|.
+             ..|.|.|.|.
+             .#+-+-+-+.
+             .+----++#.
+             #+----+|..
+             ......#v..".lines().map(|l| format!("{}\n", l.trim())).collect::<String>()
+        );
+    }
+
+    #[test]
+    fn can_step_through_the_walk() {
+        let mut grid = Grid::from_str(&fixture()).unwrap();
+
+        assert_eq!(
+            grid.walk().take(7).collect::<Vec<_>>(),
+            vec![
+                WalkEvent::Moved(Coord { x: 4, y: 5 }, Direction::Up),
+                WalkEvent::Moved(Coord { x: 4, y: 4 }, Direction::Up),
+                WalkEvent::Moved(Coord { x: 4, y: 3 }, Direction::Up),
+                WalkEvent::Moved(Coord { x: 4, y: 2 }, Direction::Up),
+                WalkEvent::Moved(Coord { x: 4, y: 1 }, Direction::Up),
+                WalkEvent::Turned(Direction::Right),
+                WalkEvent::Moved(Coord { x: 5, y: 1 }, Direction::Right),
+            ]
+        );
+
+        assert_eq!(grid.guard, Some(Coord { x: 5, y: 1 }));
+    }
+
+    #[test]
+    fn ends_the_walk_when_the_guard_exits() {
+        let mut grid = Grid::from_str(&fixture()).unwrap();
+        let mut walk = grid.walk();
+        let events: Vec<WalkEvent> = walk.by_ref().collect();
+
+        assert_eq!(events.last(), Some(&WalkEvent::Exited(Coord { x: 7, y: 9 })));
+
+        assert_eq!(
+            events.iter().filter(|e| matches!(e, WalkEvent::Turned(_))).count(),
+            10
+        );
+
+        assert_eq!(walk.next(), None);
+    }
+
+    #[test]
+    fn detects_when_the_guard_walks_in_a_loop() {
+        let mut grid = Grid::from_str(&fixture().replace(".#..^", ".#.#^")).unwrap();
+
+        assert_eq!(grid.patrol(), PatrolResult::Looped(Coord { x: 4, y: 6 }));
+    }
+
+    #[test]
+    fn finds_the_obstructions_which_trap_the_guard() {
+        let grid = Grid::from_str(&fixture()).unwrap();
+
+        assert_eq!(
+            grid.find_looping_obstructions(),
+            vec![
+                Coord { x: 3, y: 6 },
+                Coord { x: 6, y: 7 },
+                Coord { x: 7, y: 7 },
+                Coord { x: 1, y: 8 },
+                Coord { x: 3, y: 8 },
+                Coord { x: 7, y: 9 },
+            ]
+        );
+    }
+
+    #[test]
+    fn exits_cleanly_from_every_side_of_the_map() {
+        let cases = [
+            ("^..\n...\n...", Coord { x: 0, y: 0 }, 1),
+            ("..>\n...\n...", Coord { x: 2, y: 0 }, 1),
+            ("...\n...\n.v.", Coord { x: 1, y: 2 }, 1),
+            ("...\n<..\n...", Coord { x: 0, y: 1 }, 1),
+            ("...\n.>.\n...", Coord { x: 2, y: 1 }, 2),
+            ("...\n..<\n...", Coord { x: 0, y: 1 }, 3),
+            (".<.\n...\n...", Coord { x: 0, y: 0 }, 2),
+            ("...\n^..\n...", Coord { x: 0, y: 0 }, 2),
+        ];
+
+        for (map, exit, visited) in cases {
+            let mut grid = Grid::from_str(map).unwrap();
+
+            assert_eq!(grid.walk().last(), Some(WalkEvent::Exited(exit)), "{}", map);
+            assert_eq!(grid.visited_coords().len(), visited, "{}", map);
+        }
+    }
+
+    #[test]
+    fn turns_at_the_edge_of_the_map_without_wrapping() {
+        let mut grid = Grid::from_str(&unindent(
+            "..>#
+             ....
+             ...."
+        )).unwrap();
+
+        assert_eq!(grid.patrol(), PatrolResult::Exited);
+        assert_eq!(grid.visited_coords(), vec![
+            Coord { x: 2, y: 0 },
+            Coord { x: 2, y: 1 },
+            Coord { x: 2, y: 2 },
+        ]);
+    }
+
+    #[test]
+    fn rejects_uneven_and_empty_grids() {
+        for map in ["...\n.v", "...\n...\n.v", ".v\n...", "...\n\n...", "", "\n"] {
+            assert_eq!(Grid::from_str(map).err(), Some(ParseGridError), "{:?}", map);
+        }
+
+        assert_eq!(Grid::from_str("..\n.^\n\n").unwrap().to_string(), "..\n.^\n");
+        assert_eq!(Grid::from_str(" .^\n...").err(), Some(ParseGridError));
+    }
+
+    #[test]
+    fn finds_the_same_obstructions_using_a_jump_table() {
+        let grid = Grid::from_str(&fixture()).unwrap();
+
+        assert_eq!(
+            grid.find_looping_obstructions_fast(),
+            grid.find_looping_obstructions()
+        );
+    }
+
+    #[test]
+    fn finds_no_obstructions_without_a_guard() {
+        let grid = Grid::from_str("...\n.#.").unwrap();
+
+        assert_eq!(grid.find_looping_obstructions(), vec![]);
+        assert_eq!(grid.find_looping_obstructions_fast(), vec![]);
+    }
+
+    #[test]
+    fn detects_when_the_guard_is_boxed_in() {
+        let mut grid = Grid::from_str(&unindent(
+            ".#.
+             #^#
+             .#."
+        )).unwrap();
+
+        assert_eq!(grid.patrol(), PatrolResult::Looped(Coord { x: 1, y: 1 }));
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{operate, Expression, MathOperator};
+
+// Whether the countdown solver has to use every number, or may leave some out.
+#[derive(Debug, PartialEq, Clone)]
+pub enum NumberUse {
+    All,
+    Subset,
+}
+
+// The expression which gets closest to an equation's test value.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Countdown {
+    pub expression: Expression,
+    pub value: u64,
+    pub distance: u64,
+}
+
+impl Countdown {
+    pub fn reached(&self) -> bool {
+        self.distance == 0
+    }
+}
+
+fn consider(value: u64, expression: &Expression, target: u64, best: &mut Option<Countdown>) {
+    let distance = value.abs_diff(target);
+
+    if best.as_ref().is_none_or(|b| distance < b.distance) {
+        *best = Some(Countdown {
+            expression: expression.clone(),
+            value,
+            distance,
+        });
+    }
+}
+
+// Repeatedly replaces two of the items with the result of combining them,
+// trying every pair in either order with every operator. This covers every
+// ordering and bracketing of the numbers, so the search grows very quickly
+// and is only practical for a handful of numbers.
+fn search(
+    items: &[(u64, Expression)],
+    target: u64,
+    operators: &[MathOperator],
+    number_use: &NumberUse,
+    best: &mut Option<Countdown>,
+) {
+    for (i, (left, left_expression)) in items.iter().enumerate() {
+        for (j, (right, right_expression)) in items.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+
+            for operator in operators {
+                if operator.commutative() && j < i {
+                    continue;
+                }
+
+                let Some(value) = operate(*left, operator, *right) else {
+                    continue;
+                };
+                let expression = Expression::Operation(
+                    Box::new(left_expression.clone()),
+                    operator.clone(),
+                    Box::new(right_expression.clone()),
+                );
+                let remaining: Vec<(u64, Expression)> = items.iter()
+                    .enumerate()
+                    .filter(|(k, _)| *k != i && *k != j)
+                    .map(|(_, item)| item.clone())
+                    .chain([(value, expression.clone())])
+                    .collect();
+
+                if *number_use == NumberUse::Subset || remaining.len() == 1 {
+                    consider(value, &expression, target, best);
+                }
+
+                if best.as_ref().is_some_and(|b| b.reached()) {
+                    return;
+                }
+
+                search(&remaining, target, operators, number_use, best);
+            }
+        }
+    }
+}
+
+// Finds the expression closest to target made from numbers in any order and
+// with any bracketing. Returns None if there are no numbers to use.
+pub fn countdown(
+    numbers: &[u32],
+    target: u64,
+    operators: &[MathOperator],
+    number_use: &NumberUse,
+) -> Option<Countdown> {
+    let items: Vec<(u64, Expression)> = numbers.iter()
+        .map(|n| (u64::from(*n), Expression::Number(u64::from(*n))))
+        .collect();
+    let mut best = None;
+
+    if *number_use == NumberUse::Subset || items.len() == 1 {
+        for (value, expression) in &items {
+            consider(*value, expression, target, &mut best);
+        }
+    }
+
+    search(&items, target, operators, number_use, &mut best);
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::MathOperator::{Add, Multiply, Subtract, Divide};
+
+    #[test]
+    fn reorders_and_brackets_the_numbers() {
+        let operators = [Add, Subtract, Multiply, Divide];
+        let result = countdown(&[4, 7, 8, 8], 24, &operators, &NumberUse::All).unwrap();
+
+        assert!(result.reached());
+        assert_eq!(result.value, 24);
+        assert_eq!(result.expression.value(), Some(24));
+    }
+
+    #[test]
+    fn finds_the_closest_value_when_unreachable() {
+        let result = countdown(&[1, 2, 3], 1000, &[Add, Multiply], &NumberUse::All).unwrap();
+
+        assert!(!result.reached());
+        assert_eq!(result.value, 9);
+        assert_eq!(result.distance, 991);
+        assert_eq!(result.expression.to_string(), "3 * (1 + 2)");
+    }
+
+    #[test]
+    fn may_leave_numbers_out() {
+        let result = countdown(&[2, 3, 100], 6, &[Add, Multiply], &NumberUse::All).unwrap();
+
+        assert!(!result.reached());
+
+        let result = countdown(&[2, 3, 100], 6, &[Add, Multiply], &NumberUse::Subset).unwrap();
+
+        assert!(result.reached());
+        assert_eq!(result.expression.to_string(), "2 * 3");
+
+        let result = countdown(&[2, 3, 100], 100, &[Add, Multiply], &NumberUse::Subset).unwrap();
+
+        assert_eq!(result.expression, Expression::Number(100));
+    }
+
+    #[test]
+    fn needs_numbers_to_work_with() {
+        assert_eq!(countdown(&[], 6, &[Add], &NumberUse::Subset), None);
+    }
+}
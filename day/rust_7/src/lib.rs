@@ -1,68 +1,264 @@
-use std::{num::ParseIntError, str::FromStr};
-
-#[derive(PartialEq, Clone, Debug)]
-pub enum MathOperator {
-    Add,
-    Multiply,
-    Concat,
+use std::{collections::BTreeMap, fmt, num::ParseIntError, str::FromStr, thread};
+
+mod countdown;
+mod operator;
+mod precedence;
+
+pub use countdown::{Countdown, NumberUse};
+pub use operator::{operate, unoperate, Inverse, MathOperator};
+pub use precedence::{evaluate, ConcatPrecedence, Evaluation, Expression};
+
+use precedence::visit_solutions;
+
+// Calls visit with the value and operator sequence of every left-to-right
+// evaluation of numbers that doesn't overflow. Stops as soon as visit returns
+// true, and returns whether it did.
+fn visit_evaluations(
+    numbers: &[u32],
+    operators: &[MathOperator],
+    visit: &mut impl FnMut(u64, &[MathOperator]) -> bool,
+) -> bool {
+    match numbers.split_first() {
+        Some((first, rest)) => {
+            walk_evaluations(u64::from(*first), rest, operators, &mut Vec::new(), visit)
+        },
+        None => false,
+    }
 }
 
-impl MathOperator {
-    pub fn from_usize(i: &usize) -> Option<Self> {
-        match i {
-            0 => Some(MathOperator::Add),
-            1 => Some(MathOperator::Multiply),
-            2 => Some(MathOperator::Concat),
-            _ => None,
+fn walk_evaluations(
+    total: u64,
+    numbers: &[u32],
+    operators: &[MathOperator],
+    chosen: &mut Vec<MathOperator>,
+    visit: &mut impl FnMut(u64, &[MathOperator]) -> bool,
+) -> bool {
+    let (next, rest) = match numbers.split_first() {
+        Some(split) => split,
+        None => return visit(total, chosen),
+    };
+
+    for operator in operators {
+        if let Some(total) = operate(total, operator, u64::from(*next)) {
+            chosen.push(operator.clone());
+
+            let stopped = walk_evaluations(total, rest, operators, chosen, visit);
+
+            chosen.pop();
+
+            if stopped {
+                return true;
+            }
         }
     }
+
+    false
 }
 
-fn operate(left: u64, operator: &MathOperator, right: u64) -> u64 {
-    match operator {
-        MathOperator::Add => left + right,
-        MathOperator::Multiply => left * right,
-        MathOperator::Concat => (left.to_string() + &right.to_string()).parse().unwrap()
+// Works back from the rightmost number, undoing each operator in turn, so any
+// branch which cannot reach the target is abandoned as early as possible.
+// Where an operator can't be undone, the numbers to its left are evaluated
+// forwards instead.
+fn reachable(numbers: &[u32], target: u64, operators: &[MathOperator]) -> bool {
+    let (last, rest) = match numbers.split_last() {
+        Some(split) => split,
+        None => return false,
+    };
+    let last = u64::from(*last);
+
+    if rest.is_empty() {
+        return target == last;
     }
+
+    operators.iter().any(|operator| match unoperate(target, operator, last) {
+        Inverse::Left(left) => reachable(rest, left, operators),
+        Inverse::Impossible => false,
+        Inverse::Ambiguous => {
+            visit_evaluations(rest, operators, &mut |left, _| {
+                operate(left, operator, last) == Some(target)
+            })
+        },
+    })
 }
 
-fn add_one(digits: &mut Vec<usize>, radix: usize) -> bool {
-    for i in (0..digits.len()).rev() {
-        if digits[i] < radix - 1 {
-            digits[i] += 1;
-            for y in (i+1)..digits.len() {
-                digits[y] = 0;
-            }
-            return true;
+// Finds every operator sequence which takes numbers to target. The operators
+// are chosen from the right, so chosen holds them in reverse.
+fn search(
+    numbers: &[u32],
+    target: u64,
+    operators: &[MathOperator],
+    chosen: &mut Vec<MathOperator>,
+    found: &mut Vec<Vec<MathOperator>>,
+) {
+    let (last, rest) = match numbers.split_last() {
+        Some(split) => split,
+        None => return,
+    };
+    let last = u64::from(*last);
+
+    if rest.is_empty() {
+        if target == last {
+            found.push(chosen.iter().rev().cloned().collect());
+        }
+        return;
+    }
+
+    for operator in operators {
+        match unoperate(target, operator, last) {
+            Inverse::Left(left) => {
+                chosen.push(operator.clone());
+                search(rest, left, operators, chosen, found);
+                chosen.pop();
+            },
+            Inverse::Impossible => (),
+            Inverse::Ambiguous => {
+                visit_evaluations(rest, operators, &mut |left, prefix| {
+                    if operate(left, operator, last) == Some(target) {
+                        let mut sequence = prefix.to_vec();
+
+                        sequence.push(operator.clone());
+                        sequence.extend(chosen.iter().rev().cloned());
+                        found.push(sequence);
+                    }
+                    false
+                });
+            },
         }
     }
-    false
 }
 
-fn combination_grid(width: usize, radix: usize) -> Vec<Vec<usize>> {
-    let mut combinations = vec![vec![0; width]];
+fn count(numbers: &[u32], target: u64, operators: &[MathOperator]) -> usize {
+    let (last, rest) = match numbers.split_last() {
+        Some(split) => split,
+        None => return 0,
+    };
+    let last = u64::from(*last);
 
-    loop {
-        let mut row = combinations.last().unwrap().clone();
+    if rest.is_empty() {
+        return usize::from(target == last);
+    }
+
+    operators.iter()
+        .map(|operator| match unoperate(target, operator, last) {
+            Inverse::Left(left) => count(rest, left, operators),
+            Inverse::Impossible => 0,
+            Inverse::Ambiguous => {
+                let mut total = 0;
+
+                visit_evaluations(rest, operators, &mut |left, _| {
+                    if operate(left, operator, last) == Some(target) {
+                        total += 1;
+                    }
+                    false
+                });
+
+                total
+            },
+        })
+        .sum()
+}
+
+// An equation with the operators that solve it. Solutions evaluated left to
+// right are shown without brackets; any other grouping is bracketed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Solution {
+    pub test_value: u64,
+    pub numbers: Vec<u32>,
+    pub operators: Vec<MathOperator>,
+    pub evaluation: Evaluation,
+}
 
-        if add_one(&mut row, radix) {
-            combinations.push(row);
-        } else {
-            break;
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.evaluation != Evaluation::LeftToRight {
+            let expression = Expression::new(&self.numbers, &self.operators, &self.evaluation);
+
+            if let Some(expression) = expression {
+                return write!(f, "{} = {}", expression, self.test_value);
+            }
+        }
+
+        let mut to_write = String::new();
+
+        for (i, number) in self.numbers.iter().enumerate() {
+            if i > 0 {
+                to_write.push_str(&format!(" {} ", self.operators[i - 1]));
+            }
+            to_write.push_str(&number.to_string());
         }
-    }
 
-    combinations
+        write!(f, "{} = {}", to_write, self.test_value)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
-pub enum ParseEquationError {
+pub enum ParseEquationErrorKind {
     NoTestValue,
     NoNumbers,
     InvalidTestValue(ParseIntError),
+    // Something other than the colon after the test value.
+    UnexpectedToken,
     InvalidNumber(ParseIntError),
 }
 
+// Where an equation failed to parse. Lines and columns count from 1, and the
+// token is the text at fault (empty if something was missing).
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseEquationError {
+    pub kind: ParseEquationErrorKind,
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+}
+
+impl ParseEquationError {
+    fn new(kind: ParseEquationErrorKind, s: &str, offset: usize, token: &str) -> Self {
+        Self {
+            kind,
+            line: 1,
+            column: s[..offset].chars().count() + 1,
+            token: token.to_string(),
+        }
+    }
+
+    pub fn on_line(self, line: usize) -> Self {
+        Self { line, ..self }
+    }
+}
+
+impl fmt::Display for ParseEquationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let problem = match &self.kind {
+            ParseEquationErrorKind::NoTestValue => "missing test value".to_string(),
+            ParseEquationErrorKind::NoNumbers => "missing numbers".to_string(),
+            ParseEquationErrorKind::InvalidTestValue(err) => {
+                format!("invalid test value {:?} ({})", self.token, err)
+            },
+            ParseEquationErrorKind::UnexpectedToken => {
+                format!("unexpected {:?} after the test value", self.token)
+            },
+            ParseEquationErrorKind::InvalidNumber(err) => {
+                format!("invalid number {:?} ({})", self.token, err)
+            },
+        };
+
+        write!(f, "line {}, column {}: {}", self.line, self.column, problem)
+    }
+}
+
+// Splits s on whitespace, keeping the byte offset of each piece.
+fn tokens(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    s.char_indices()
+        .filter(move |(i, c)| {
+            !c.is_whitespace() && (*i == 0 || s[..*i].ends_with(char::is_whitespace))
+        })
+        .map(move |(start, _)| {
+            let end = s[start..].find(char::is_whitespace).map_or(s.len(), |len| start + len);
+
+            (start, &s[start..end])
+        })
+}
+
 #[derive(Debug)]
 pub struct Equation {
     pub test_value: u64,
@@ -70,31 +266,120 @@ pub struct Equation {
 }
 
 impl Equation {
-    pub fn solvable(&self, operators: &Vec<MathOperator>) -> bool {
-        let operator_combos: Vec<Vec<MathOperator>> = combination_grid(self.numbers.len() - 1, operators.len())
-            .iter()
-            .map(|row| row.iter().map(|i| MathOperator::from_usize(&i).unwrap()).collect())
-            .collect();
+    pub fn solvable(&self, operators: &[MathOperator]) -> bool {
+        reachable(&self.numbers, self.test_value, operators)
+    }
 
-        for mut operator_row in operator_combos {
-            let mut total: Option<u64> = None;
+    pub fn solutions(&self, operators: &[MathOperator]) -> Vec<Solution> {
+        let mut found = Vec::new();
 
-            for i in 1..self.numbers.len() {
-                let left = total.or(Some(self.numbers[i - 1].into())).unwrap();
-                let operator = operator_row.pop().unwrap();
-                let right = self.numbers[i].into();
+        search(&self.numbers, self.test_value, operators, &mut Vec::new(), &mut found);
 
-                let result = operate(left, &operator, right);
+        self.to_solutions(found, &Evaluation::LeftToRight)
+    }
 
-                total.replace(result);
-            }
+    pub fn count_solutions(&self, operators: &[MathOperator]) -> usize {
+        count(&self.numbers, self.test_value, operators)
+    }
 
-            if total == Some(self.test_value) {
-                return true;
+    // Every value the numbers can reach left to right, along with how many
+    // operator sequences reach it. Sequences which overflow are left out, and
+    // counts too big for a usize stop at usize::MAX.
+    pub fn reachable_values(&self, operators: &[MathOperator]) -> BTreeMap<u64, usize> {
+        let mut values = BTreeMap::new();
+
+        if let Some((first, rest)) = self.numbers.split_first() {
+            values.insert(u64::from(*first), 1);
+
+            for number in rest {
+                let mut next_values = BTreeMap::new();
+
+                for (value, count) in &values {
+                    for operator in operators {
+                        if let Some(next) = operate(*value, operator, u64::from(*number)) {
+                            let total: &mut usize = next_values.entry(next).or_insert(0);
+
+                            *total = total.saturating_add(*count);
+                        }
+                    }
+                }
+
+                values = next_values;
             }
         }
 
-        false
+        values
+    }
+
+    // The reachable value nearest the test value, preferring the lower of two
+    // equally near values.
+    pub fn closest_reachable_value(&self, operators: &[MathOperator]) -> Option<u64> {
+        let values = self.reachable_values(operators);
+        let below = values.range(..=self.test_value).next_back().map(|(value, _)| *value);
+        let above = values.range(self.test_value..).next().map(|(value, _)| *value);
+
+        match (below, above) {
+            (Some(below), Some(above)) => {
+                if self.test_value - below <= above - self.test_value {
+                    Some(below)
+                } else {
+                    Some(above)
+                }
+            },
+            (below, above) => below.or(above),
+        }
+    }
+
+    pub fn solvable_with(&self, operators: &[MathOperator], evaluation: &Evaluation) -> bool {
+        if *evaluation == Evaluation::LeftToRight {
+            return self.solvable(operators);
+        }
+
+        visit_solutions(&self.numbers, self.test_value, operators, evaluation, &mut |_| true)
+    }
+
+    pub fn solutions_with(
+        &self,
+        operators: &[MathOperator],
+        evaluation: &Evaluation,
+    ) -> Vec<Solution> {
+        if *evaluation == Evaluation::LeftToRight {
+            return self.solutions(operators);
+        }
+
+        let mut found = Vec::new();
+
+        visit_solutions(&self.numbers, self.test_value, operators, evaluation, &mut |chosen| {
+            found.push(chosen.to_vec());
+            false
+        });
+
+        self.to_solutions(found, evaluation)
+    }
+
+    // Unlike the other solvers, the numbers may be used in any order and with
+    // any bracketing. Returns None if the equation has no numbers.
+    pub fn countdown(
+        &self,
+        operators: &[MathOperator],
+        number_use: &NumberUse,
+    ) -> Option<Countdown> {
+        countdown::countdown(&self.numbers, self.test_value, operators, number_use)
+    }
+
+    fn to_solutions(
+        &self,
+        found: Vec<Vec<MathOperator>>,
+        evaluation: &Evaluation,
+    ) -> Vec<Solution> {
+        found.into_iter()
+            .map(|operators| Solution {
+                test_value: self.test_value,
+                numbers: self.numbers.clone(),
+                operators,
+                evaluation: evaluation.clone(),
+            })
+            .collect()
     }
 }
 
@@ -102,29 +387,61 @@ impl FromStr for Equation {
     type Err = ParseEquationError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.trim().split(":");
-        let mut numbers: Vec<u32> = Vec::new();
+        let (test_value_part, number_part) = match s.split_once(':') {
+            Some((test_value_part, number_part)) => (test_value_part, Some(number_part)),
+            None => (s, None),
+        };
+
+        let mut test_value_tokens = tokens(test_value_part);
+        let test_value = match test_value_tokens.next() {
+            Some((offset, token)) => token.parse().map_err(|err| {
+                let kind = ParseEquationErrorKind::InvalidTestValue(err);
 
-        let test_value_string = parts.next().unwrap();
+                ParseEquationError::new(kind, s, offset, token)
+            })?,
+            None => {
+                let kind = ParseEquationErrorKind::NoTestValue;
+                let offset = s.len() - s.trim_start().len();
 
-        let test_value = match test_value_string.parse() {
-            Ok(number) => number,
-            Err(err) => return Err(ParseEquationError::InvalidTestValue(err))
+                return Err(ParseEquationError::new(kind, s, offset, ""));
+            },
         };
 
-        let number_strings = parts.next();
+        if let Some((offset, token)) = test_value_tokens.next() {
+            let kind = ParseEquationErrorKind::UnexpectedToken;
 
-        if None == number_strings {
-            return Err(ParseEquationError::NoNumbers);
+            return Err(ParseEquationError::new(kind, s, offset, token));
         }
 
-        for number_string in number_strings.unwrap().trim().split(" ") {
-            match number_string.parse::<u32>() {
+        let number_offset = test_value_part.len() + 1;
+        let number_part = match number_part {
+            Some(number_part) => number_part,
+            None => {
+                let kind = ParseEquationErrorKind::NoNumbers;
+
+                return Err(ParseEquationError::new(kind, s, s.trim_end().len(), ""));
+            },
+        };
+
+        let mut numbers: Vec<u32> = Vec::new();
+
+        for (offset, token) in tokens(number_part) {
+            match token.parse::<u32>() {
                 Ok(number) => numbers.push(number),
-                Err(err) => return Err(ParseEquationError::InvalidNumber(err))
+                Err(err) => {
+                    let kind = ParseEquationErrorKind::InvalidNumber(err);
+
+                    return Err(ParseEquationError::new(kind, s, number_offset + offset, token));
+                },
             };
         }
 
+        if numbers.is_empty() {
+            let kind = ParseEquationErrorKind::NoNumbers;
+
+            return Err(ParseEquationError::new(kind, s, s.trim_end().len(), ""));
+        }
+
         Ok(Self {
             test_value,
             numbers
@@ -132,10 +449,85 @@ impl FromStr for Equation {
     }
 }
 
+// The total of the test values of the solvable equations in some input, along
+// with any lines which couldn't be parsed. Blank lines are ignored.
+#[derive(Debug, PartialEq)]
+pub struct Calibration {
+    pub total: u64,
+    pub errors: Vec<ParseEquationError>,
+}
+
+fn calibrate_lines<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+    operators: &[MathOperator],
+) -> Calibration {
+    let mut calibration = Calibration {
+        total: 0,
+        errors: Vec::new(),
+    };
+
+    for (i, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match Equation::from_str(line) {
+            Ok(equation) => {
+                if equation.solvable(operators) {
+                    calibration.total += equation.test_value;
+                }
+            },
+            Err(err) => calibration.errors.push(err.on_line(i + 1)),
+        }
+    }
+
+    calibration
+}
+
+pub fn calibrate(input: &str, operators: &[MathOperator]) -> Calibration {
+    calibrate_lines(input.lines().enumerate(), operators)
+}
+
+// Like calibrate, but deals the lines out round robin to the given number of
+// threads (at least one). Errors are still reported in line order.
+pub fn calibrate_parallel(
+    input: &str,
+    operators: &[MathOperator],
+    threads: usize,
+) -> Calibration {
+    let threads = threads.max(1);
+    let lines: Vec<(usize, &str)> = input.lines().enumerate().collect();
+
+    let parts: Vec<Calibration> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let lines = &lines;
+
+                scope.spawn(move || {
+                    let share = lines.iter().skip(worker).step_by(threads).copied();
+
+                    calibrate_lines(share, operators)
+                })
+            })
+            .collect();
+
+        workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+    });
+
+    let mut calibration = Calibration {
+        total: parts.iter().map(|part| part.total).sum(),
+        errors: parts.into_iter().flat_map(|part| part.errors).collect(),
+    };
+
+    calibration.errors.sort_by_key(|err| err.line);
+
+    calibration
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::MathOperator::{Add, Multiply, Concat};
+    use super::MathOperator::{Add, Multiply, Concat, Subtract, Divide, Power, Xor, Or};
 
     fn fixture() -> String {
         "190: 10 19
@@ -149,6 +541,283 @@ mod tests {
          292: 11 6 16 20".to_string()
     }
 
+    #[test]
+    fn only_uses_the_given_operators() {
+        let equation = Equation {
+            test_value: 156,
+            numbers: vec![15, 6]
+        };
+
+        assert!(equation.solvable(&[Concat]));
+        assert!(!equation.solvable(&[Add, Multiply]));
+
+        let equation = Equation {
+            test_value: 190,
+            numbers: vec![10, 19]
+        };
+
+        assert!(!equation.solvable(&[Concat]));
+        assert!(!equation.solvable(&[Add, Concat]));
+        assert!(equation.solvable(&[Multiply, Concat]));
+
+        let equation = Equation {
+            test_value: 7290,
+            numbers: vec![6, 8, 6, 15]
+        };
+
+        assert!(equation.solvable(&[Multiply, Concat]));
+        assert!(equation.solvable(&[Concat, Multiply]));
+        assert!(!equation.solvable(&[Add, Concat]));
+
+        let equation = Equation {
+            test_value: 192,
+            numbers: vec![17, 8, 14]
+        };
+
+        assert!(equation.solvable(&[Add, Concat]));
+        assert!(equation.solvable(&[Concat, Add]));
+        assert!(!equation.solvable(&[Multiply, Concat]));
+        assert!(!equation.solvable(&[]));
+    }
+
+    #[test]
+    fn lists_the_operators_which_solve_an_equation() {
+        let equation = Equation::from_str("3267: 81 40 27").unwrap();
+        let solutions = equation.solutions(&[Add, Multiply]);
+
+        assert_eq!(
+            solutions.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+            vec!["81 * 40 + 27 = 3267", "81 + 40 * 27 = 3267"]
+        );
+        assert_eq!(equation.count_solutions(&[Add, Multiply]), 2);
+
+        let equation = Equation::from_str("7290: 6 8 6 15").unwrap();
+
+        assert_eq!(
+            equation.solutions(&[Add, Multiply, Concat])[0].to_string(),
+            "6 * 8 || 6 * 15 = 7290"
+        );
+
+        for solution in equation.solutions(&[Add, Multiply, Concat]) {
+            let total = solution.operators.iter()
+                .zip(&solution.numbers[1..])
+                .fold(u64::from(solution.numbers[0]), |total, (operator, number)| {
+                    operate(total, operator, u64::from(*number)).unwrap()
+                });
+
+            assert_eq!(total, solution.test_value);
+        }
+    }
+
+    #[test]
+    fn lists_no_solutions_when_unsolvable() {
+        let equation = Equation::from_str("161011: 16 10 13").unwrap();
+
+        assert!(equation.solutions(&[Add, Multiply, Concat]).is_empty());
+        assert_eq!(equation.count_solutions(&[Add, Multiply, Concat]), 0);
+    }
+
+    #[test]
+    fn counts_every_solution_after_multiplying_by_zero() {
+        let equation = Equation {
+            test_value: 0,
+            numbers: vec![17, 8, 0]
+        };
+
+        assert_eq!(equation.count_solutions(&[Add, Multiply]), 2);
+        assert_eq!(equation.solutions(&[Add, Multiply]).len(), 2);
+        assert_eq!(equation.count_solutions(&[Add, Multiply, Concat]), 3);
+    }
+
+    #[test]
+    fn treats_overflow_as_unsolvable() {
+        let equation = Equation {
+            test_value: 0,
+            numbers: vec![u32::MAX, u32::MAX, u32::MAX, 0]
+        };
+
+        assert!(!equation.solvable(&[Multiply]));
+        assert!(equation.solvable(&[Add, Multiply]));
+        assert_eq!(equation.count_solutions(&[Add, Multiply]), 2);
+        assert_eq!(
+            equation.solutions(&[Add, Multiply])[0].to_string(),
+            "4294967295 + 4294967295 + 4294967295 * 0 = 0"
+        );
+
+        let equation = Equation {
+            test_value: u64::MAX,
+            numbers: vec![u32::MAX; 8]
+        };
+
+        assert!(!equation.solvable(&[Add, Multiply, Concat]));
+    }
+
+    #[test]
+    fn solves_with_any_choice_of_operators() {
+        let equation = Equation::from_str("10: 20 15 5").unwrap();
+
+        assert!(equation.solvable(&[Add, Subtract]));
+        assert_eq!(equation.solutions(&[Add, Subtract])[0].to_string(), "20 - 15 + 5 = 10");
+
+        let equation = Equation::from_str("5: 20 4 1").unwrap();
+
+        assert!(equation.solvable(&[Divide, Multiply]));
+        assert!(!equation.solvable(&[Add, Multiply, Concat]));
+
+        let equation = Equation::from_str("1025: 2 10 1").unwrap();
+
+        assert!(equation.solvable(&[Power, Add]));
+        assert!(!equation.solvable(&[Power, Multiply]));
+
+        let equation = Equation::from_str("7: 5 3").unwrap();
+
+        assert!(equation.solvable(&[Or]));
+        assert!(!equation.solvable(&[Xor]));
+        assert!(equation.solvable(&[Xor, Or]));
+        assert_eq!(equation.count_solutions(&[Add, Xor, Or]), 1);
+
+        let equation = Equation::from_str("1: 9 6 0").unwrap();
+
+        let solutions = equation.solutions(&[Subtract, Divide, Power]);
+
+        assert_eq!(
+            solutions.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+            vec!["9 - 6 ** 0 = 1", "9 ** 6 ** 0 = 1"]
+        );
+    }
+
+    #[test]
+    fn solves_with_multiplication_before_addition() {
+        let evaluation = Evaluation::Precedence(ConcatPrecedence::Lowest);
+        let equation = Equation::from_str("1161: 81 40 27").unwrap();
+
+        assert!(!equation.solvable(&[Add, Multiply]));
+        assert!(equation.solvable_with(&[Add, Multiply], &evaluation));
+        assert_eq!(
+            equation.solutions_with(&[Add, Multiply], &evaluation)[0].to_string(),
+            "81 + (40 * 27) = 1161"
+        );
+
+        let equation = Equation::from_str("3267: 81 40 27").unwrap();
+
+        assert_eq!(
+            equation.solutions_with(&[Add, Multiply], &evaluation).iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>(),
+            vec!["(81 * 40) + 27 = 3267"]
+        );
+        assert_eq!(
+            equation.solutions_with(&[Add, Multiply], &Evaluation::LeftToRight),
+            equation.solutions(&[Add, Multiply])
+        );
+    }
+
+    #[test]
+    fn solves_with_configurable_concat_precedence() {
+        let equation = Equation::from_str("37: 2 3 14").unwrap();
+        let highest = Evaluation::Precedence(ConcatPrecedence::Highest);
+        let lowest = Evaluation::Precedence(ConcatPrecedence::Lowest);
+
+        assert!(equation.solvable_with(&[Concat, Add], &highest));
+        assert!(!equation.solvable_with(&[Concat, Add], &lowest));
+        assert_eq!(
+            equation.solutions_with(&[Concat, Add], &highest)[0].to_string(),
+            "(2 || 3) + 14 = 37"
+        );
+    }
+
+    #[test]
+    fn solves_anything_solvable_left_to_right_as_a_countdown() {
+        for line in fixture().lines() {
+            let equation = Equation::from_str(line).unwrap();
+            let countdown = equation.countdown(&[Add, Multiply], &NumberUse::All).unwrap();
+
+            if equation.solvable(&[Add, Multiply]) {
+                assert!(countdown.reached(), "{}", line);
+            }
+            assert_eq!(countdown.expression.value(), Some(countdown.value));
+        }
+    }
+
+    #[test]
+    fn lists_every_reachable_value() {
+        let equation = Equation::from_str("3267: 81 40 27").unwrap();
+
+        assert_eq!(
+            equation.reachable_values(&[Add, Multiply]),
+            BTreeMap::from([(148, 1), (3267, 2), (87480, 1)])
+        );
+
+        let equation = Equation::from_str("7290: 6 8 6 15").unwrap();
+        let operators = [Add, Multiply, Concat];
+        let values = equation.reachable_values(&operators);
+
+        assert_eq!(values.values().sum::<usize>(), 27);
+        assert_eq!(values.get(&7290).copied(), Some(equation.count_solutions(&operators)));
+
+        // 2^69 sequences all reach 0.
+        let equation = Equation::from_str(&format!("1:{}", " 0".repeat(70))).unwrap();
+
+        assert_eq!(
+            equation.reachable_values(&[Add, Multiply]),
+            BTreeMap::from([(0, usize::MAX)])
+        );
+        assert_eq!(equation.closest_reachable_value(&[Add, Multiply]), Some(0));
+    }
+
+    #[test]
+    fn finds_the_closest_reachable_value() {
+        let equation = Equation::from_str("83: 17 5").unwrap();
+
+        assert_eq!(equation.closest_reachable_value(&[Add, Multiply]), Some(85));
+
+        let equation = Equation::from_str("12: 4 4").unwrap();
+
+        assert_eq!(equation.closest_reachable_value(&[Add, Multiply]), Some(8));
+        assert_eq!(equation.closest_reachable_value(&[Multiply]), Some(16));
+        assert_eq!(equation.closest_reachable_value(&[Concat]), Some(44));
+        assert_eq!(equation.closest_reachable_value(&[Subtract]), Some(0));
+
+        let equation = Equation::from_str("190: 10 19").unwrap();
+
+        assert_eq!(equation.closest_reachable_value(&[Add, Multiply]), Some(190));
+        assert_eq!(equation.closest_reachable_value(&[Divide]), None);
+    }
+
+    #[test]
+    fn solves_long_equations() {
+        let equation = Equation {
+            test_value: 2u64.pow(30),
+            numbers: vec![2; 30]
+        };
+
+        assert!(equation.solvable(&[Add, Multiply, Concat]));
+
+        let equation = Equation {
+            test_value: 2u64.pow(30) + 1,
+            numbers: vec![2; 30]
+        };
+
+        assert!(!equation.solvable(&[Add, Multiply, Concat]));
+    }
+
+    #[test]
+    fn solves_equations_containing_zeroes() {
+        let equation = Equation {
+            test_value: 0,
+            numbers: vec![17, 8, 0]
+        };
+
+        assert!(equation.solvable(&[Add, Multiply]));
+
+        let equation = Equation {
+            test_value: 170,
+            numbers: vec![17, 0]
+        };
+
+        assert!(equation.solvable(&[Add, Multiply, Concat]));
+    }
+
     #[test]
     fn parses_an_equation_row() {
         for line in fixture().lines() {
@@ -237,7 +906,7 @@ mod tests {
     #[test]
     fn errors_on_no_numbers() {
         let res = Equation::from_str("123");
-        assert_eq!(res.unwrap_err(), ParseEquationError::NoNumbers);
+        assert_eq!(res.unwrap_err().kind, ParseEquationErrorKind::NoNumbers);
     }
 
     #[test]
@@ -245,4 +914,82 @@ mod tests {
         let res = Equation::from_str("123: twelve, 42");
         assert!(!res.is_ok());
     }
+
+    #[test]
+    fn reports_where_an_equation_failed_to_parse() {
+        let err = Equation::from_str(":12, 42").unwrap_err();
+
+        assert_eq!(err.kind, ParseEquationErrorKind::NoTestValue);
+        assert_eq!((err.line, err.column, err.token.as_str()), (1, 1, ""));
+
+        let err = Equation::from_str("123: 4 twelve, 42").unwrap_err();
+
+        assert!(matches!(err.kind, ParseEquationErrorKind::InvalidNumber(_)));
+        assert_eq!((err.column, err.token.as_str()), (8, "twelve,"));
+
+        let err = Equation::from_str("  12x: 4").unwrap_err();
+
+        assert!(matches!(err.kind, ParseEquationErrorKind::InvalidTestValue(_)));
+        assert_eq!((err.column, err.token.as_str()), (3, "12x"));
+
+        let err = Equation::from_str("12 34: 5").unwrap_err();
+
+        assert_eq!(err.kind, ParseEquationErrorKind::UnexpectedToken);
+        assert_eq!((err.column, err.token.as_str()), (4, "34"));
+        assert_eq!(err.to_string(), "line 1, column 4: unexpected \"34\" after the test value");
+
+        let err = Equation::from_str("12: 4\u{3000}é5").unwrap_err();
+
+        assert_eq!((err.column, err.token.as_str()), (7, "é5"));
+
+        let err = Equation::from_str("123:  ").unwrap_err();
+
+        assert_eq!(err.kind, ParseEquationErrorKind::NoNumbers);
+        assert_eq!(err.column, 5);
+
+        assert_eq!(
+            err.on_line(7).to_string(),
+            "line 7, column 5: missing numbers"
+        );
+    }
+
+    #[test]
+    fn parses_and_solves_single_number_equations() {
+        let equation = Equation::from_str("5: 5").unwrap();
+
+        assert_eq!(equation.numbers, vec![5]);
+        assert!(equation.solvable(&[Add, Multiply]));
+        assert_eq!(equation.count_solutions(&[Add, Multiply]), 1);
+        assert_eq!(equation.solutions(&[Add])[0].to_string(), "5 = 5");
+        assert!(!Equation::from_str("5: 6").unwrap().solvable(&[Add, Multiply]));
+    }
+
+    #[test]
+    fn calibrates_around_bad_lines() {
+        let input = fixture()
+            .replace("83: 17 5", "83 17 5")
+            .replace("292: 11 6 16 20", "292: 11 six 16 20");
+        let calibration = calibrate(&input, &[Add, Multiply]);
+
+        assert_eq!(calibration.total, 190 + 3267);
+        assert_eq!(
+            calibration.errors.iter().map(|e| (e.line, e.token.as_str())).collect::<Vec<_>>(),
+            vec![(3, "17"), (9, "six")]
+        );
+        assert_eq!(calibrate(&fixture(), &[Add, Multiply]).total, 3749);
+    }
+
+    #[test]
+    fn calibrates_in_parallel_to_the_same_result() {
+        let input = fixture().replace("292: 11 6 16 20", "292 11 6 16 20") + "\nx: 1\n5: 5";
+        let operators = [Add, Multiply, Concat];
+        let sequential = calibrate(&input, &operators);
+
+        assert_eq!(sequential.total, 11387 - 292 + 5);
+        assert_eq!(sequential.errors.len(), 2);
+
+        for threads in [0, 1, 2, 3, 8, 64] {
+            assert_eq!(calibrate_parallel(&input, &operators, threads), sequential);
+        }
+    }
 }
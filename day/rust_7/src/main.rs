@@ -1,24 +1,26 @@
-use std::{fs::File, io::{self, BufRead}, str::FromStr};
-use rust_7::{Equation, MathOperator::{self, Add, Multiply, Concat}};
+use std::{env, fs, thread};
+use rust_7::{calibrate_parallel, MathOperator::{self, Add, Multiply, Concat}};
 
-fn solve_file(operators: Vec<MathOperator>) -> u64 {
-    let file = File::open("./input").unwrap();
-    let mut total = 0;
+// Skips any lines which can't be parsed, reporting them on stderr.
+fn solve_file(operators: Vec<MathOperator>, threads: usize) -> u64 {
+    let input = fs::read_to_string("./input").unwrap();
+    let calibration = calibrate_parallel(&input, &operators, threads);
 
-    for line_result in io::BufReader::new(file).lines() {
-        if let Ok(line) = line_result {
-            let equation = Equation::from_str(&line).unwrap();
-
-            if equation.solvable(&operators) {
-                total += equation.test_value;
-            }
-        }
+    for err in &calibration.errors {
+        eprintln!("skipped {}", err);
     }
 
-    total
+    calibration.total
 }
 
+// The number of threads can be given as the first argument, and defaults to
+// one per available core.
 fn main() {
-    println!("part 1 solution: {}", solve_file(vec![Add, Multiply]));
-    println!("part 2 solution: {}", solve_file(vec![Add, Multiply, Concat]));
+    let threads = match env::args().nth(1) {
+        Some(arg) => arg.parse().expect("thread count should be a number"),
+        None => thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+
+    println!("part 1 solution: {}", solve_file(vec![Add, Multiply], threads));
+    println!("part 2 solution: {}", solve_file(vec![Add, Multiply, Concat], threads));
 }
@@ -0,0 +1,233 @@
+use std::fmt;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum MathOperator {
+    Add,
+    Multiply,
+    Concat,
+    Subtract,
+    Divide,
+    Power,
+    Xor,
+    Or,
+}
+
+impl MathOperator {
+    pub fn from_usize(i: &usize) -> Option<Self> {
+        match i {
+            0 => Some(MathOperator::Add),
+            1 => Some(MathOperator::Multiply),
+            2 => Some(MathOperator::Concat),
+            3 => Some(MathOperator::Subtract),
+            4 => Some(MathOperator::Divide),
+            5 => Some(MathOperator::Power),
+            6 => Some(MathOperator::Xor),
+            7 => Some(MathOperator::Or),
+            _ => None,
+        }
+    }
+
+    // Whether swapping the operands never changes the result.
+    pub fn commutative(&self) -> bool {
+        matches!(
+            self,
+            MathOperator::Add | MathOperator::Multiply | MathOperator::Xor | MathOperator::Or
+        )
+    }
+}
+
+impl fmt::Display for MathOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MathOperator::Add => write!(f, "+"),
+            MathOperator::Multiply => write!(f, "*"),
+            MathOperator::Concat => write!(f, "||"),
+            MathOperator::Subtract => write!(f, "-"),
+            MathOperator::Divide => write!(f, "/"),
+            MathOperator::Power => write!(f, "**"),
+            MathOperator::Xor => write!(f, "^"),
+            MathOperator::Or => write!(f, "|"),
+        }
+    }
+}
+
+// What unoperate knows about the left operand of an operation.
+#[derive(Debug, PartialEq)]
+pub enum Inverse {
+    // Exactly one left operand gives the result.
+    Left(u64),
+    // No left operand gives the result.
+    Impossible,
+    // Several left operands may give the result (or all of them, as when
+    // multiplying by zero), so the left side has to be evaluated to find out.
+    Ambiguous,
+}
+
+impl From<Option<u64>> for Inverse {
+    fn from(left: Option<u64>) -> Self {
+        match left {
+            Some(left) => Inverse::Left(left),
+            None => Inverse::Impossible,
+        }
+    }
+}
+
+// The power of ten just above the highest digit of n, so that concatenating
+// onto n is a multiplication by it. None if that doesn't fit in a u64.
+fn magnitude(n: u64) -> Option<u64> {
+    10u64.checked_pow(n.checked_ilog10().unwrap_or(0) + 1)
+}
+
+// The integer whose exponent-th power is n, if there is one.
+fn root(n: u64, exponent: u32) -> Option<u64> {
+    let estimate = (n as f64).powf(1.0 / exponent as f64).round() as u64;
+
+    (estimate.saturating_sub(1)..=estimate.saturating_add(1))
+        .find(|candidate| candidate.checked_pow(exponent) == Some(n))
+}
+
+// Returns None if the operation is not defined for these operands, either
+// because it would overflow or, for Subtract and Divide, because the result
+// would not be a whole positive number.
+pub fn operate(left: u64, operator: &MathOperator, right: u64) -> Option<u64> {
+    match operator {
+        MathOperator::Add => left.checked_add(right),
+        MathOperator::Multiply => left.checked_mul(right),
+        MathOperator::Concat => match magnitude(right) {
+            Some(magnitude) => left.checked_mul(magnitude)?.checked_add(right),
+            None if left == 0 => Some(right),
+            None => None,
+        },
+        MathOperator::Subtract => left.checked_sub(right),
+        MathOperator::Divide => {
+            if right != 0 && left.is_multiple_of(right) {
+                Some(left / right)
+            } else {
+                None
+            }
+        },
+        MathOperator::Power => left.checked_pow(u32::try_from(right).ok()?),
+        MathOperator::Xor => Some(left ^ right),
+        MathOperator::Or => Some(left | right),
+    }
+}
+
+// The inverse of operate: works out the left operand which, combined with
+// right, gives result.
+pub fn unoperate(result: u64, operator: &MathOperator, right: u64) -> Inverse {
+    match operator {
+        MathOperator::Add => result.checked_sub(right).into(),
+        MathOperator::Multiply => {
+            if right == 0 && result == 0 {
+                Inverse::Ambiguous
+            } else if right != 0 && result.is_multiple_of(right) {
+                Inverse::Left(result / right)
+            } else {
+                Inverse::Impossible
+            }
+        },
+        MathOperator::Concat => match magnitude(right) {
+            Some(magnitude) if result % magnitude == right => Inverse::Left(result / magnitude),
+            None if result == right => Inverse::Left(0),
+            _ => Inverse::Impossible,
+        },
+        MathOperator::Subtract => result.checked_add(right).into(),
+        MathOperator::Divide => {
+            if right == 0 {
+                Inverse::Impossible
+            } else {
+                result.checked_mul(right).into()
+            }
+        },
+        MathOperator::Power => match u32::try_from(right) {
+            Ok(0) if result == 1 => Inverse::Ambiguous,
+            Ok(0) | Err(_) => Inverse::Impossible,
+            Ok(1) => Inverse::Left(result),
+            Ok(exponent) => root(result, exponent).into(),
+        },
+        MathOperator::Xor => Inverse::Left(result ^ right),
+        MathOperator::Or => {
+            if right & !result != 0 {
+                Inverse::Impossible
+            } else if right == 0 {
+                Inverse::Left(result)
+            } else {
+                Inverse::Ambiguous
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::MathOperator::{Add, Multiply, Concat, Subtract, Divide, Power, Xor, Or};
+
+    const OPERATORS: [MathOperator; 8] = [Add, Multiply, Concat, Subtract, Divide, Power, Xor, Or];
+
+    #[test]
+    fn undoes_each_operator() {
+        assert_eq!(unoperate(3267, &Add, 27), Inverse::Left(3240));
+        assert_eq!(unoperate(3267, &Multiply, 27), Inverse::Left(121));
+        assert_eq!(unoperate(3267, &Multiply, 40), Inverse::Impossible);
+        assert_eq!(unoperate(156, &Concat, 6), Inverse::Left(15));
+        assert_eq!(unoperate(156, &Concat, 56), Inverse::Left(1));
+        assert_eq!(unoperate(156, &Concat, 7), Inverse::Impossible);
+        assert_eq!(unoperate(150, &Concat, 0), Inverse::Left(15));
+        assert_eq!(unoperate(5, &Add, 6), Inverse::Impossible);
+        assert_eq!(unoperate(5, &Subtract, 6), Inverse::Left(11));
+        assert_eq!(unoperate(5, &Divide, 6), Inverse::Left(30));
+        assert_eq!(unoperate(5, &Divide, 0), Inverse::Impossible);
+        assert_eq!(unoperate(81, &Power, 4), Inverse::Left(3));
+        assert_eq!(unoperate(82, &Power, 4), Inverse::Impossible);
+        assert_eq!(unoperate(6, &Xor, 3), Inverse::Left(5));
+        assert_eq!(unoperate(6, &Or, 1), Inverse::Impossible);
+        assert_eq!(unoperate(7, &Or, 3), Inverse::Ambiguous);
+        assert_eq!(unoperate(0, &Multiply, 0), Inverse::Ambiguous);
+        assert_eq!(unoperate(1, &Power, 0), Inverse::Ambiguous);
+
+        for left in [0, 1, 2, 9, 10, 15, 1234] {
+            for right in [0, 1, 2, 9, 10, 15, 1234] {
+                for operator in OPERATORS {
+                    if let Some(result) = operate(left, &operator, right) {
+                        match unoperate(result, &operator, right) {
+                            Inverse::Left(undone) => assert_eq!(undone, left),
+                            Inverse::Ambiguous => (),
+                            Inverse::Impossible => panic!("{} {} {}", left, operator, right),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn operates_without_overflowing() {
+        assert_eq!(operate(u64::MAX, &Add, 1), None);
+        assert_eq!(operate(u64::MAX / 2, &Multiply, 3), None);
+        assert_eq!(operate(u64::MAX / 10, &Concat, 9), None);
+        assert_eq!(operate(0, &Concat, u64::MAX), Some(u64::MAX));
+        assert_eq!(operate(184467440737095516, &Concat, 15), Some(u64::MAX));
+        assert_eq!(operate(2, &Power, 64), None);
+        assert_eq!(unoperate(u64::MAX, &Concat, u64::MAX), Inverse::Left(0));
+        assert_eq!(unoperate(u64::MAX, &Subtract, 1), Inverse::Impossible);
+        assert_eq!(unoperate(u64::MAX, &Divide, 2), Inverse::Impossible);
+
+        for left in [0, 1, 7, 10, 99, 100, 4294967295] {
+            for right in [0, 1, 7, 10, 99, 100, 4294967295] {
+                let concatenated = (left.to_string() + &right.to_string()).parse().ok();
+
+                assert_eq!(operate(left, &Concat, right), concatenated);
+            }
+        }
+    }
+
+    #[test]
+    fn only_subtracts_and_divides_to_whole_positive_numbers() {
+        assert_eq!(operate(5, &Subtract, 6), None);
+        assert_eq!(operate(6, &Subtract, 6), Some(0));
+        assert_eq!(operate(12, &Divide, 5), None);
+        assert_eq!(operate(12, &Divide, 0), None);
+        assert_eq!(operate(12, &Divide, 4), Some(3));
+    }
+}
@@ -0,0 +1,331 @@
+use std::fmt;
+
+use crate::{operate, MathOperator};
+
+// Where Concat sits among the other operators when evaluating by precedence.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConcatPrecedence {
+    Lowest,
+    Additive,
+    Multiplicative,
+    Highest,
+}
+
+// How the operators of an equation are grouped when it is evaluated.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Evaluation {
+    // Strictly left to right, as the puzzle describes.
+    LeftToRight,
+    // Power binds tightest (and to the right), then Multiply and Divide, then
+    // Add and Subtract, then Xor and finally Or. Concat binds as configured.
+    Precedence(ConcatPrecedence),
+}
+
+impl Evaluation {
+    // Higher binds tighter. Every operator binds equally when going left to right.
+    fn binding(&self, operator: &MathOperator) -> u8 {
+        let concat = match self {
+            Evaluation::LeftToRight => return 0,
+            Evaluation::Precedence(concat) => concat,
+        };
+
+        match operator {
+            MathOperator::Or => 1,
+            MathOperator::Xor => 2,
+            MathOperator::Add | MathOperator::Subtract => 3,
+            MathOperator::Multiply | MathOperator::Divide => 4,
+            MathOperator::Power => 5,
+            MathOperator::Concat => match concat {
+                ConcatPrecedence::Lowest => 0,
+                ConcatPrecedence::Additive => 3,
+                ConcatPrecedence::Multiplicative => 4,
+                ConcatPrecedence::Highest => 6,
+            },
+        }
+    }
+
+    fn right_associative(&self, operator: &MathOperator) -> bool {
+        *operator == MathOperator::Power && *self != Evaluation::LeftToRight
+    }
+}
+
+// The operands and operators still waiting on a tighter binding operator to
+// their right. Numbers are pushed one at a time, so a search can extend it as
+// it chooses each operator.
+#[derive(Debug, Clone)]
+struct Pending<T> {
+    operands: Vec<T>,
+    operators: Vec<MathOperator>,
+}
+
+impl<T> Pending<T> {
+    fn new(first: T) -> Self {
+        Self {
+            operands: vec![first],
+            operators: Vec::new(),
+        }
+    }
+
+    // Combines pending operations which bind at least as tightly as next, or
+    // all of them if there is no next operator.
+    fn reduce(
+        &mut self,
+        evaluation: &Evaluation,
+        next: Option<&MathOperator>,
+        combine: &impl Fn(T, &MathOperator, T) -> Option<T>,
+    ) -> Option<()> {
+        while let Some(top) = self.operators.last() {
+            if let Some(next) = next {
+                let top_binding = evaluation.binding(top);
+                let next_binding = evaluation.binding(next);
+
+                if top_binding < next_binding
+                    || (top_binding == next_binding && evaluation.right_associative(next)) {
+                    break;
+                }
+            }
+
+            let operator = self.operators.pop()?;
+            let right = self.operands.pop()?;
+            let left = self.operands.pop()?;
+
+            self.operands.push(combine(left, &operator, right)?);
+        }
+
+        Some(())
+    }
+
+    fn push(
+        &mut self,
+        evaluation: &Evaluation,
+        operator: MathOperator,
+        operand: T,
+        combine: &impl Fn(T, &MathOperator, T) -> Option<T>,
+    ) -> Option<()> {
+        self.reduce(evaluation, Some(&operator), combine)?;
+        self.operators.push(operator);
+        self.operands.push(operand);
+
+        Some(())
+    }
+
+    fn finish(
+        mut self,
+        evaluation: &Evaluation,
+        combine: &impl Fn(T, &MathOperator, T) -> Option<T>,
+    ) -> Option<T> {
+        self.reduce(evaluation, None, combine)?;
+        self.operands.pop()
+    }
+}
+
+// Evaluates numbers joined by operators, returning None if any step overflows
+// or is otherwise undefined.
+pub fn evaluate(
+    numbers: &[u32],
+    operators: &[MathOperator],
+    evaluation: &Evaluation,
+) -> Option<u64> {
+    let (first, rest) = numbers.split_first()?;
+    let mut pending = Pending::new(u64::from(*first));
+
+    for (operator, number) in operators.iter().zip(rest) {
+        pending.push(evaluation, operator.clone(), u64::from(*number), &operate)?;
+    }
+
+    pending.finish(evaluation, &operate)
+}
+
+// Calls visit with every operator sequence which takes numbers to target
+// under the given evaluation. Stops as soon as visit returns true, and
+// returns whether it did.
+pub fn visit_solutions(
+    numbers: &[u32],
+    target: u64,
+    operators: &[MathOperator],
+    evaluation: &Evaluation,
+    visit: &mut impl FnMut(&[MathOperator]) -> bool,
+) -> bool {
+    match numbers.split_first() {
+        Some((first, rest)) => {
+            let pending = Pending::new(u64::from(*first));
+
+            walk_solutions(pending, rest, target, operators, evaluation, &mut Vec::new(), visit)
+        },
+        None => false,
+    }
+}
+
+fn walk_solutions(
+    pending: Pending<u64>,
+    numbers: &[u32],
+    target: u64,
+    operators: &[MathOperator],
+    evaluation: &Evaluation,
+    chosen: &mut Vec<MathOperator>,
+    visit: &mut impl FnMut(&[MathOperator]) -> bool,
+) -> bool {
+    let (next, rest) = match numbers.split_first() {
+        Some(split) => split,
+        None => {
+            return pending.finish(evaluation, &operate) == Some(target) && visit(chosen);
+        },
+    };
+
+    for operator in operators {
+        let mut extended = pending.clone();
+
+        if extended.push(evaluation, operator.clone(), u64::from(*next), &operate).is_none() {
+            continue;
+        }
+
+        chosen.push(operator.clone());
+
+        let stopped = walk_solutions(extended, rest, target, operators, evaluation, chosen, visit);
+
+        chosen.pop();
+
+        if stopped {
+            return true;
+        }
+    }
+
+    false
+}
+
+// An expression with its grouping made explicit.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expression {
+    Number(u64),
+    Operation(Box<Expression>, MathOperator, Box<Expression>),
+}
+
+impl Expression {
+    pub fn new(
+        numbers: &[u32],
+        operators: &[MathOperator],
+        evaluation: &Evaluation,
+    ) -> Option<Self> {
+        let combine = |left, operator: &MathOperator, right| {
+            Some(Expression::Operation(Box::new(left), operator.clone(), Box::new(right)))
+        };
+        let (first, rest) = numbers.split_first()?;
+        let mut pending = Pending::new(Expression::Number(u64::from(*first)));
+
+        for (operator, number) in operators.iter().zip(rest) {
+            let operand = Expression::Number(u64::from(*number));
+
+            pending.push(evaluation, operator.clone(), operand, &combine)?;
+        }
+
+        pending.finish(evaluation, &combine)
+    }
+
+    pub fn value(&self) -> Option<u64> {
+        match self {
+            Expression::Number(n) => Some(*n),
+            Expression::Operation(left, operator, right) => {
+                operate(left.value()?, operator, right.value()?)
+            },
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Number(n) => write!(f, "{}", n),
+            Expression::Operation(left, operator, right) => {
+                for (i, operand) in [left, right].into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " {} ", operator)?;
+                    }
+                    if let Expression::Operation(..) = **operand {
+                        write!(f, "({})", operand)?;
+                    } else {
+                        write!(f, "{}", operand)?;
+                    }
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::MathOperator::{Add, Multiply, Concat, Power};
+
+    const MULTIPLY_FIRST: Evaluation = Evaluation::Precedence(ConcatPrecedence::Lowest);
+
+    #[test]
+    fn evaluates_left_to_right() {
+        let evaluation = Evaluation::LeftToRight;
+
+        assert_eq!(evaluate(&[81, 40, 27], &[Add, Multiply], &evaluation), Some(3267));
+        assert_eq!(evaluate(&[2, 3, 2], &[Power, Power], &evaluation), Some(64));
+    }
+
+    #[test]
+    fn evaluates_multiplication_before_addition() {
+        assert_eq!(evaluate(&[81, 40, 27], &[Add, Multiply], &MULTIPLY_FIRST), Some(1161));
+        assert_eq!(evaluate(&[81, 40, 27], &[Multiply, Add], &MULTIPLY_FIRST), Some(3267));
+        assert_eq!(evaluate(&[1, 2, 3, 4], &[Add, Multiply, Add], &MULTIPLY_FIRST), Some(11));
+        assert_eq!(evaluate(&[2, 3, 2], &[Power, Power], &MULTIPLY_FIRST), Some(512));
+    }
+
+    #[test]
+    fn evaluates_concat_at_the_configured_precedence() {
+        let cases: [(&[u32], &[MathOperator], [u64; 4]); 3] = [
+            (&[2, 3, 14], &[Concat, Add], [217, 37, 37, 37]),
+            (&[2, 3, 4], &[Concat, Multiply], [212, 212, 92, 92]),
+            (&[2, 3, 4], &[Multiply, Concat], [64, 64, 64, 68]),
+        ];
+
+        for (numbers, operators, expected) in cases {
+            let precedences = [
+                ConcatPrecedence::Lowest,
+                ConcatPrecedence::Additive,
+                ConcatPrecedence::Multiplicative,
+                ConcatPrecedence::Highest,
+            ];
+
+            for (precedence, value) in precedences.into_iter().zip(expected) {
+                let evaluation = Evaluation::Precedence(precedence);
+
+                assert_eq!(
+                    evaluate(numbers, operators, &evaluation),
+                    Some(value),
+                    "{:?}",
+                    evaluation
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shows_the_grouping() {
+        let operators = [Add, Multiply, Add];
+        let expression = Expression::new(&[1, 2, 3, 4], &operators, &MULTIPLY_FIRST).unwrap();
+
+        assert_eq!(expression.to_string(), "(1 + (2 * 3)) + 4");
+        assert_eq!(expression.value(), Some(11));
+
+        let expression = Expression::new(&[1, 2, 3, 4], &operators, &Evaluation::LeftToRight);
+
+        assert_eq!(expression.unwrap().to_string(), "((1 + 2) * 3) + 4");
+    }
+
+    #[test]
+    fn finds_every_solution() {
+        let mut found = Vec::new();
+
+        visit_solutions(&[1, 2, 3, 4], 11, &[Add, Multiply], &MULTIPLY_FIRST, &mut |operators| {
+            found.push(operators.to_vec());
+            false
+        });
+
+        assert_eq!(found, vec![vec![Add, Multiply, Add]]);
+    }
+}
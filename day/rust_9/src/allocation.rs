@@ -0,0 +1,220 @@
+use std::ops::Range;
+
+use crate::{ChecksumOverflow, Disk};
+
+// Where whole files are moved when defragmenting a disk.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AllocationPolicy {
+    // The leftmost free span that fits, as compress_part_two does.
+    FirstFit,
+    // The smallest free span that fits, leftmost first.
+    BestFit,
+    // The largest free span, leftmost first.
+    WorstFit,
+    // The first span that fits after the previous file moved, wrapping round
+    // to the start of the disk if there isn't one.
+    NextFit,
+    // Files are moved right instead, starting from the start of the disk, to
+    // the end of the rightmost free span that fits.
+    PackTowardEnd,
+}
+
+impl AllocationPolicy {
+    pub const ALL: [AllocationPolicy; 5] = [
+        AllocationPolicy::FirstFit,
+        AllocationPolicy::BestFit,
+        AllocationPolicy::WorstFit,
+        AllocationPolicy::NextFit,
+        AllocationPolicy::PackTowardEnd,
+    ];
+
+    // Picks the index of one of the spans, which are in order, that is at
+    // least size long.
+    fn choose(&self, spans: &[Range<usize>], size: usize, cursor: usize) -> Option<usize> {
+        let mut fits = spans.iter().enumerate().filter(|(_, span)| span.len() >= size);
+        let chosen = match self {
+            AllocationPolicy::FirstFit => fits.next(),
+            AllocationPolicy::BestFit => fits.min_by_key(|(_, span)| span.len()),
+            AllocationPolicy::WorstFit => fits.rev().max_by_key(|(_, span)| span.len()),
+            AllocationPolicy::NextFit => {
+                let first = fits.next();
+
+                first
+                    .into_iter()
+                    .chain(fits)
+                    .find(|(_, span)| span.start >= cursor)
+                    .or(first)
+            },
+            AllocationPolicy::PackTowardEnd => fits.next_back(),
+        };
+
+        chosen.map(|(i, _)| i)
+    }
+}
+
+// How a disk turned out after defragmenting it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Defragmentation {
+    pub policy: AllocationPolicy,
+    pub checksum: Result<u64, ChecksumOverflow>,
+    pub moves: usize,
+    // Free spans with file blocks on both sides, and how many blocks they
+    // add up to.
+    pub gaps: usize,
+    pub gap_blocks: usize,
+}
+
+impl Disk {
+    // Moves whole files into free space according to the policy. Files move
+    // left, last file first, except when packing toward the end, where they
+    // move right, first file first. Space a file frees is never wanted: moving
+    // left, the files still to move all lie before it and only look further
+    // left, and moving right, they all lie after it and only look further
+    // right.
+    pub fn defragment(&mut self, policy: &AllocationPolicy) -> Defragmentation {
+        let toward_end = *policy == AllocationPolicy::PackTowardEnd;
+        let mut files = self.occupied_block_ranges();
+        let mut free_spans = self.free_block_ranges();
+        let mut cursor = 0;
+        let mut moves = 0;
+
+        if !toward_end {
+            files.reverse();
+        }
+
+        for file in files {
+            // Only spans on the side the file is moving towards will do.
+            let split = free_spans.partition_point(|span| span.start < file.start);
+            let (offset, candidates) = if toward_end {
+                (split, &free_spans[split..])
+            } else {
+                (0, &free_spans[..split])
+            };
+
+            let Some(chosen) = policy.choose(candidates, file.len(), cursor) else {
+                continue;
+            };
+            let chosen = offset + chosen;
+
+            let span = &mut free_spans[chosen];
+            let destination = if toward_end {
+                span.end -= file.len();
+                span.end..span.end + file.len()
+            } else {
+                span.start += file.len();
+                span.start - file.len()..span.start
+            };
+
+            if span.start == span.end {
+                free_spans.remove(chosen);
+            }
+
+            cursor = destination.end;
+            moves += 1;
+
+            for (from, to) in file.zip(destination) {
+                self.map.swap(from, to);
+            }
+        }
+
+        let mut gaps = 0;
+        let mut gap_blocks = 0;
+        let files = self.occupied_block_ranges();
+
+        for pair in files.windows(2) {
+            if pair[0].end < pair[1].start {
+                gaps += 1;
+                gap_blocks += pair[1].start - pair[0].end;
+            }
+        }
+
+        Defragmentation {
+            policy: *policy,
+            checksum: self.checksum(),
+            moves,
+            gaps,
+            gap_blocks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::tests::{example_disk, DISK_MAP};
+
+    fn defragment(disk_map: &str, policy: &AllocationPolicy) -> (String, Defragmentation) {
+        let mut disk = Disk::from_str(disk_map).unwrap();
+        let defragmentation = disk.defragment(policy);
+
+        (disk.block_string(), defragmentation)
+    }
+
+    #[test]
+    fn first_fit_matches_part_two() {
+        let (blocks, defragmentation) = defragment(DISK_MAP, &AllocationPolicy::FirstFit);
+
+        assert_eq!(blocks, "00992111777.44.333....5555.6666.....8888..");
+        assert_eq!(defragmentation.checksum, Ok(2858));
+        assert_eq!(defragmentation.moves, 4);
+        assert_eq!((defragmentation.gaps, defragmentation.gap_blocks), (5, 12));
+    }
+
+    #[test]
+    fn places_files_by_policy() {
+        let cases = [
+            ("1215111", AllocationPolicy::FirstFit, "0321........"),
+            ("1215111", AllocationPolicy::BestFit, "021.......3."),
+            ("1215111", AllocationPolicy::WorstFit, "01..32......"),
+            ("1114103", AllocationPolicy::FirstFit, "021333....."),
+            ("1114103", AllocationPolicy::NextFit, "01.3332...."),
+            (
+                DISK_MAP,
+                AllocationPolicy::PackTowardEnd,
+                "........111..00333.44.5555.6666.7772888899",
+            ),
+        ];
+
+        for (disk_map, policy, expected) in cases {
+            let (blocks, _) = defragment(disk_map, &policy);
+
+            assert_eq!(blocks, expected, "{:?} {}", policy, disk_map);
+        }
+
+        let (_, defragmentation) = defragment("1114103", &AllocationPolicy::NextFit);
+
+        assert_eq!(
+            defragmentation,
+            Defragmentation {
+                policy: AllocationPolicy::NextFit,
+                checksum: Ok(49),
+                moves: 3,
+                gaps: 1,
+                gap_blocks: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn reports_a_checksum_that_overflows() {
+        let disk_map = format!("20[{}]1", usize::MAX);
+        let (_, defragmentation) = defragment(&disk_map, &AllocationPolicy::FirstFit);
+
+        assert_eq!(defragmentation.checksum, Err(ChecksumOverflow));
+    }
+
+    #[test]
+    fn reports_on_every_policy() {
+        for policy in AllocationPolicy::ALL {
+            let mut disk = example_disk();
+            let defragmentation = disk.defragment(&policy);
+
+            assert_eq!(defragmentation.policy, policy);
+            assert_eq!(defragmentation.checksum, disk.checksum());
+            assert_eq!(disk.block_string().len(), 42);
+            assert_eq!(disk.block_string().matches('.').count(), 14);
+        }
+    }
+}
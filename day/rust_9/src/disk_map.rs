@@ -0,0 +1,298 @@
+use std::{fmt, io::{self, Read}};
+
+use crate::DiskBlock;
+
+#[derive(Debug)]
+pub enum ParseDiskError {
+    // A byte which is neither a digit nor trailing whitespace, and where it
+    // was found counting from 0.
+    InvalidCharacter { offset: usize, found: u8 },
+    // The disk map stopped partway through giving a file its id.
+    UnexpectedEnd { offset: usize },
+    Read(io::Error),
+}
+
+impl fmt::Display for ParseDiskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseDiskError::InvalidCharacter { offset, found } => {
+                write!(f, "expected a digit at offset {} but found ", offset)?;
+
+                if found.is_ascii() {
+                    write!(f, "'{}'", found.escape_ascii())
+                } else {
+                    write!(f, "byte {:#04x}", found)
+                }
+            },
+            ParseDiskError::UnexpectedEnd { offset } => {
+                write!(f, "expected a file id or length at offset {}", offset)
+            },
+            ParseDiskError::Read(err) => write!(f, "couldn't read disk map: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for ParseDiskError {
+    fn from(err: io::Error) -> Self {
+        ParseDiskError::Read(err)
+    }
+}
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// Where the parser is within the disk map.
+enum Expecting {
+    File,
+    // Inside the brackets of an explicit file id, with the digits so far.
+    Id(Option<usize>),
+    // The length of a file whose id has been given.
+    FileWithId(usize),
+    FreeSpace,
+}
+
+// Reads a disk map a chunk at a time, calling visit with each run of blocks it
+// describes: files and free space alternately, starting with file 0. Trailing
+// whitespace is ignored.
+//
+// Besides the dense notation, a file's length may be preceded by its id in
+// brackets, as in "2[9]2". Files without one take the id after the previous
+// file's, so a plain dense map numbers its files from 0 as usual.
+pub fn parse_runs(
+    mut reader: impl Read,
+    mut visit: impl FnMut(DiskBlock, usize),
+) -> Result<(), ParseDiskError> {
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut offset = 0;
+    let mut next_id = 0;
+    let mut expecting = Expecting::File;
+    let mut whitespace_from: Option<(usize, u8)> = None;
+
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+
+        for &byte in &chunk[..read] {
+            if byte.is_ascii_whitespace() {
+                whitespace_from.get_or_insert((offset, byte));
+                offset += 1;
+                continue;
+            }
+
+            if let Some((offset, found)) = whitespace_from {
+                return Err(ParseDiskError::InvalidCharacter { offset, found });
+            }
+
+            let digit = byte.is_ascii_digit().then(|| usize::from(byte - b'0'));
+
+            expecting = match (expecting, digit) {
+                (Expecting::File, None) if byte == b'[' => Expecting::Id(None),
+                (Expecting::Id(id), Some(digit)) => {
+                    let id = id.unwrap_or(0).checked_mul(10).and_then(|id| id.checked_add(digit));
+
+                    match id {
+                        Some(id) => Expecting::Id(Some(id)),
+                        None => {
+                            return Err(ParseDiskError::InvalidCharacter { offset, found: byte });
+                        },
+                    }
+                },
+                (Expecting::Id(Some(id)), None) if byte == b']' => Expecting::FileWithId(id),
+                (Expecting::File, Some(len)) => {
+                    visit(DiskBlock::File { id: next_id }, len);
+                    next_id = next_id.saturating_add(1);
+                    Expecting::FreeSpace
+                },
+                (Expecting::FileWithId(id), Some(len)) => {
+                    visit(DiskBlock::File { id }, len);
+                    next_id = id.saturating_add(1);
+                    Expecting::FreeSpace
+                },
+                (Expecting::FreeSpace, Some(len)) => {
+                    visit(DiskBlock::FreeSpace, len);
+                    Expecting::File
+                },
+                _ => return Err(ParseDiskError::InvalidCharacter { offset, found: byte }),
+            };
+
+            offset += 1;
+        }
+    }
+
+    match expecting {
+        Expecting::File | Expecting::FreeSpace => Ok(()),
+        Expecting::Id(_) | Expecting::FileWithId(_) => {
+            let offset = whitespace_from.map_or(offset, |(offset, _)| offset);
+
+            Err(ParseDiskError::UnexpectedEnd { offset })
+        },
+    }
+}
+
+// Writes runs of blocks as a disk map which parse_runs reads back into the same
+// blocks. Runs longer than 9 blocks are split up around empty files or free
+// spans, and files are only given an explicit id where the dense numbering
+// would get it wrong.
+pub fn render_runs(runs: impl IntoIterator<Item = (DiskBlock, usize)>) -> String {
+    let mut disk_map = String::new();
+    let mut next_id = 0;
+    let mut expecting = Expecting::File;
+
+    for (block, mut len) in runs {
+        while len > 0 {
+            let digit = len.min(9);
+
+            match (block, &expecting) {
+                (DiskBlock::File { id }, Expecting::File) => {
+                    if id != next_id {
+                        disk_map.push_str(&format!("[{}]", id));
+                    }
+                    next_id = id.saturating_add(1);
+                    expecting = Expecting::FreeSpace;
+                },
+                (DiskBlock::FreeSpace, Expecting::FreeSpace) => expecting = Expecting::File,
+                (DiskBlock::File { .. }, _) => {
+                    disk_map.push('0');
+                    expecting = Expecting::File;
+                    continue;
+                },
+                (DiskBlock::FreeSpace, _) => {
+                    disk_map.push('0');
+                    next_id = next_id.saturating_add(1);
+                    expecting = Expecting::FreeSpace;
+                    continue;
+                },
+            }
+
+            disk_map.push(char::from(b'0' + digit as u8));
+            len -= digit;
+        }
+    }
+
+    disk_map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hands out at most one byte per read, to split the input everywhere.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((first, rest)) if !buf.is_empty() => {
+                    buf[0] = *first;
+                    self.0 = rest;
+                    Ok(1)
+                },
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn runs(input: &[u8]) -> Result<Vec<(DiskBlock, usize)>, ParseDiskError> {
+        let mut runs = Vec::new();
+
+        parse_runs(Trickle(input), |block, len| runs.push((block, len)))?;
+
+        Ok(runs)
+    }
+
+    #[test]
+    fn reads_alternating_runs() {
+        assert_eq!(
+            runs(b"203\n").unwrap(),
+            vec![
+                (DiskBlock::File { id: 0 }, 2),
+                (DiskBlock::FreeSpace, 0),
+                (DiskBlock::File { id: 1 }, 3),
+            ]
+        );
+        assert_eq!(runs(b"").unwrap(), vec![]);
+        assert_eq!(runs(b" \r\n\t").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn rejects_anything_but_digits_and_trailing_whitespace() {
+        let cases: [(&[u8], usize, u8); 4] = [
+            (b"12a4", 2, b'a'),
+            (b"12 4", 2, b' '),
+            (b"12\n\n4\n", 2, b'\n'),
+            ("12\u{e9}".as_bytes(), 2, 0xc3),
+        ];
+
+        for (input, expected_offset, expected_found) in cases {
+            match runs(input) {
+                Err(ParseDiskError::InvalidCharacter { offset, found }) => {
+                    assert_eq!((offset, found), (expected_offset, expected_found));
+                },
+                other => panic!("{:?} parsed to {:?}", input, other),
+            }
+        }
+
+        assert_eq!(
+            runs(b"12x").unwrap_err().to_string(),
+            "expected a digit at offset 2 but found 'x'"
+        );
+        assert_eq!(
+            runs("\u{e9}".as_bytes()).unwrap_err().to_string(),
+            "expected a digit at offset 0 but found byte 0xc3"
+        );
+    }
+
+    #[test]
+    fn reads_explicit_file_ids() {
+        assert_eq!(
+            runs(b"10[7]2031").unwrap(),
+            vec![
+                (DiskBlock::File { id: 0 }, 1),
+                (DiskBlock::FreeSpace, 0),
+                (DiskBlock::File { id: 7 }, 2),
+                (DiskBlock::FreeSpace, 0),
+                (DiskBlock::File { id: 8 }, 3),
+                (DiskBlock::FreeSpace, 1),
+            ]
+        );
+
+        for (input, offset) in [(&b"[]1"[..], 1), (b"1[2]3", 1), (b"[2][3]4", 3), (b"[1x]", 2)] {
+            match runs(input) {
+                Err(ParseDiskError::InvalidCharacter { offset: found_at, .. }) => {
+                    assert_eq!(found_at, offset, "{:?}", input);
+                },
+                other => panic!("{:?} parsed to {:?}", input, other),
+            }
+        }
+
+        assert!(matches!(runs(b"12[34] "), Err(ParseDiskError::UnexpectedEnd { offset: 6 })));
+    }
+
+    #[test]
+    fn renders_runs_that_read_back_the_same() {
+        let cases = [
+            (vec![(DiskBlock::File { id: 0 }, 2), (DiskBlock::FreeSpace, 3)], "23"),
+            (vec![(DiskBlock::FreeSpace, 3), (DiskBlock::File { id: 1 }, 1)], "031"),
+            (vec![(DiskBlock::File { id: 0 }, 1), (DiskBlock::File { id: 9 }, 2)], "10[9]2"),
+            (vec![(DiskBlock::File { id: 3 }, 12)], "[3]90[3]3"),
+            (vec![(DiskBlock::File { id: 0 }, 1), (DiskBlock::FreeSpace, 14)], "1905"),
+        ];
+
+        for (blocks, expected) in cases {
+            let disk_map = render_runs(blocks.iter().copied());
+
+            assert_eq!(disk_map, expected);
+
+            let read: Vec<_> = runs(disk_map.as_bytes())
+                .unwrap()
+                .into_iter()
+                .filter(|(_, len)| *len > 0)
+                .collect();
+
+            assert_eq!(render_runs(read), disk_map);
+        }
+    }
+}
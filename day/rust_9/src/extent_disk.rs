@@ -0,0 +1,341 @@
+use std::{fmt, io::Read, str::FromStr};
+
+use crate::{
+    disk_map::{parse_runs, render_runs},
+    free_spans::FreeSpans,
+    ChecksumOverflow,
+    Disk,
+    DiskBlock,
+    ParseDiskError,
+};
+
+// A run of blocks which all hold the same file, or are all free.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Extent {
+    pub block: DiskBlock,
+    pub start: usize,
+    pub len: usize,
+}
+
+impl Extent {
+    pub fn end(&self) -> usize {
+        self.start + self.len
+    }
+}
+
+// A disk stored as runs of blocks rather than one entry per block, so its size
+// depends on how many runs there are and not on how long they are. Extents are
+// kept in order, without gaps, and neighbouring extents never hold the same
+// thing.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ExtentDisk {
+    pub extents: Vec<Extent>,
+}
+
+impl ExtentDisk {
+    // Lays out runs of blocks one after the other from the start of the disk.
+    pub fn from_runs(runs: impl IntoIterator<Item = (DiskBlock, usize)>) -> Self {
+        let mut disk = Self::default();
+
+        for (block, len) in runs {
+            disk.push(block, len);
+        }
+
+        disk
+    }
+
+    // Neither the disk map text nor the blocks are ever held in full, only the
+    // runs they make up.
+    pub fn from_reader(reader: impl Read) -> Result<Self, ParseDiskError> {
+        let mut disk = Self::default();
+
+        parse_runs(reader, |block, len| disk.push(block, len))?;
+
+        Ok(disk)
+    }
+
+    pub fn len(&self) -> usize {
+        self.extents.last().map_or(0, Extent::end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn push(&mut self, block: DiskBlock, len: usize) {
+        if len == 0 {
+            return;
+        }
+
+        match self.extents.last_mut() {
+            Some(last) if last.block == block => last.len += len,
+            _ => {
+                let start = self.len();
+
+                self.extents.push(Extent { block, start, len });
+            },
+        }
+    }
+
+    pub fn block_string(&self) -> String {
+        self.to_string()
+    }
+
+    // The disk in the notation it is parsed from, with explicit ids wherever
+    // files are out of order or in fragments.
+    pub fn disk_map(&self) -> String {
+        render_runs(self.extents.iter().map(|extent| (extent.block, extent.len)))
+    }
+
+    // The same as Disk::compress: file blocks are moved one at a time from the
+    // end of the disk into the leftmost free block. Works a run at a time.
+    pub fn compress(&mut self) {
+        let len = self.len();
+        let file_len: usize = self.extents
+            .iter()
+            .filter(|extent| extent.block != DiskBlock::FreeSpace)
+            .map(|extent| extent.len)
+            .sum();
+
+        // Everything past file_len has to move, last block first.
+        let mut movers = self.extents
+            .iter()
+            .rev()
+            .take_while(|extent| extent.end() > file_len)
+            .filter(|extent| extent.block != DiskBlock::FreeSpace)
+            .map(|extent| (extent.block, extent.end() - extent.start.max(file_len)));
+        let mut mover: Option<(DiskBlock, usize)> = None;
+        let mut compressed = Self::default();
+
+        for extent in self.extents.iter().take_while(|extent| extent.start < file_len) {
+            let len = extent.end().min(file_len) - extent.start;
+
+            if extent.block != DiskBlock::FreeSpace {
+                compressed.push(extent.block, len);
+                continue;
+            }
+
+            let mut gap = len;
+
+            while gap > 0 {
+                let (block, available) = match mover.take() {
+                    Some(mover) => mover,
+                    None => match movers.next() {
+                        Some(mover) => mover,
+                        None => break,
+                    },
+                };
+                let moved = available.min(gap);
+
+                compressed.push(block, moved);
+                gap -= moved;
+
+                if available > moved {
+                    mover = Some((block, available - moved));
+                }
+            }
+        }
+
+        compressed.push(DiskBlock::FreeSpace, len - compressed.len());
+
+        *self = compressed;
+    }
+
+    // The same as Disk::compress_part_two: whole files are moved, starting from
+    // the end of the disk, into the leftmost free span before them that fits.
+    pub fn compress_part_two(&mut self) {
+        let len = self.len();
+        let mut free_spans = FreeSpans::new(
+            self.extents
+                .iter()
+                .filter(|extent| extent.block == DiskBlock::FreeSpace)
+                .map(|extent| extent.start..extent.end()),
+        );
+        let mut files: Vec<Extent> = self.extents
+            .iter()
+            .filter(|extent| extent.block != DiskBlock::FreeSpace)
+            .copied()
+            .collect();
+
+        for file in files.iter_mut().rev() {
+            if let Some(free_range) = free_spans.take_leftmost(file.len, file.start) {
+                file.start = free_range.start;
+            }
+        }
+
+        files.sort_by_key(|file| file.start);
+
+        let mut compressed = Self::default();
+
+        for file in files {
+            compressed.push(DiskBlock::FreeSpace, file.start - compressed.len());
+            compressed.push(file.block, file.len);
+        }
+
+        compressed.push(DiskBlock::FreeSpace, len - compressed.len());
+
+        *self = compressed;
+    }
+
+    pub fn checksum(&self) -> Result<u64, ChecksumOverflow> {
+        self.checked_checksum().ok_or(ChecksumOverflow)
+    }
+
+    // Sums position times id over every file block a run at a time, returning
+    // None if the total doesn't fit in 64 bits.
+    fn checked_checksum(&self) -> Option<u64> {
+        let mut total: u64 = 0;
+
+        for extent in &self.extents {
+            if let DiskBlock::File { id } = extent.block {
+                let start = u64::try_from(extent.start).ok()?;
+                let len = u64::try_from(extent.len).ok()?;
+                let last = start.checked_add(len - 1)?;
+                // Halve whichever factor is even so the sum doesn't overflow early.
+                let positions = if len % 2 == 0 {
+                    (len / 2).checked_mul(start.checked_add(last)?)?
+                } else {
+                    len.checked_mul(start / 2 + last / 2 + (start % 2 + last % 2) / 2)?
+                };
+
+                total = total.checked_add(positions.checked_mul(u64::try_from(id).ok()?)?)?;
+            }
+        }
+
+        Some(total)
+    }
+}
+
+impl From<&Disk> for ExtentDisk {
+    fn from(disk: &Disk) -> Self {
+        Self::from_runs(disk.map.iter().map(|block| (*block, 1)))
+    }
+}
+
+impl fmt::Display for ExtentDisk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for extent in &self.extents {
+            for _ in 0..extent.len {
+                write!(f, "{}", extent.block)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ExtentDisk {
+    type Err = ParseDiskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_reader(s.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DISK_MAP;
+
+    #[test]
+    fn parses_into_runs() {
+        let disk = ExtentDisk::from_str("12101").unwrap();
+
+        assert_eq!(
+            disk.extents,
+            vec![
+                Extent { block: DiskBlock::File { id: 0 }, start: 0, len: 1 },
+                Extent { block: DiskBlock::FreeSpace, start: 1, len: 2 },
+                Extent { block: DiskBlock::File { id: 1 }, start: 3, len: 1 },
+                Extent { block: DiskBlock::File { id: 2 }, start: 4, len: 1 },
+            ]
+        );
+        assert_eq!(
+            ExtentDisk::from_str(DISK_MAP).unwrap().block_string(),
+            "00...111...2...333.44.5555.6666.777.888899"
+        );
+    }
+
+    #[test]
+    fn compresses_like_a_block_disk() {
+        let disk_maps = ["", "1", "10101", "12345", "1313165", "2030201", "14113", "90909"];
+
+        for disk_map in disk_maps.into_iter().chain(Some(DISK_MAP)) {
+            let mut disk = Disk::from_str(disk_map).unwrap();
+            let mut extent_disk = ExtentDisk::from_str(disk_map).unwrap();
+
+            assert_eq!(extent_disk, ExtentDisk::from(&disk));
+
+            disk.compress();
+            extent_disk.compress();
+
+            assert_eq!(extent_disk, ExtentDisk::from(&disk), "{}", disk_map);
+            assert_eq!(extent_disk.checksum(), disk.checksum());
+
+            let mut disk = Disk::from_str(disk_map).unwrap();
+            let mut extent_disk = ExtentDisk::from_str(disk_map).unwrap();
+
+            disk.compress_part_two();
+            extent_disk.compress_part_two();
+
+            assert_eq!(extent_disk, ExtentDisk::from(&disk), "{}", disk_map);
+            assert_eq!(extent_disk.checksum(), disk.checksum());
+        }
+    }
+
+    #[test]
+    fn handles_huge_runs() {
+        let billion = 1_000_000_000;
+        let mut disk = ExtentDisk::from_runs([
+            (DiskBlock::File { id: 0 }, billion),
+            (DiskBlock::FreeSpace, billion),
+            (DiskBlock::File { id: 1 }, billion),
+            (DiskBlock::File { id: 2 }, 3),
+        ]);
+
+        disk.compress_part_two();
+
+        assert_eq!(
+            disk.extents[1],
+            Extent { block: DiskBlock::File { id: 2 }, start: billion, len: 3 }
+        );
+        assert_eq!(disk.len(), 3 * billion + 3);
+
+        disk.compress();
+
+        assert_eq!(disk.extents.len(), 4);
+        assert_eq!(
+            disk.extents[3],
+            Extent { block: DiskBlock::FreeSpace, start: 2 * billion + 3, len: billion }
+        );
+        assert_eq!(disk.checksum(), Ok(1_500_000_008_500_000_006));
+    }
+
+    #[test]
+    fn reports_a_checksum_that_overflows() {
+        let mut disk = ExtentDisk::from_runs([
+            (DiskBlock::FreeSpace, usize::MAX / 4),
+            (DiskBlock::File { id: 1 }, 3),
+        ]);
+
+        assert_eq!(disk.checksum(), Ok(3 * (u64::MAX / 4 + 1)));
+
+        disk.push(DiskBlock::File { id: 2 }, 1);
+
+        assert_eq!(disk.checksum(), Err(ChecksumOverflow));
+    }
+
+    #[test]
+    fn round_trips_through_a_disk_map() {
+        let mut disk = ExtentDisk::from_str(DISK_MAP).unwrap();
+
+        assert_eq!(disk.disk_map(), DISK_MAP);
+
+        disk.compress();
+
+        let disk_map = disk.disk_map();
+
+        assert_eq!(disk_map, "20[9]20[8]10[1]30[8]30[2]10[7]30[3]30[6]10[4]20[6]10[5]402905");
+        assert_eq!(ExtentDisk::from_str(&disk_map).unwrap(), disk);
+        assert_eq!(ExtentDisk::from_str(&disk_map).unwrap().disk_map(), disk_map);
+    }
+}
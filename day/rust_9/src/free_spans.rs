@@ -0,0 +1,81 @@
+use std::{collections::{BTreeMap, BTreeSet}, ops::Range};
+
+// The free spans of a disk, kept as one ordered set of start positions per
+// span length. Finding the leftmost span which fits a file means checking the
+// first entry of each set at least as long as the file, so a lookup is
+// logarithmic in the number of spans times the number of distinct lengths.
+// That is few for disks parsed from dense maps, whose runs are at most nine
+// blocks, but an extent disk's spans can have any number of lengths.
+#[derive(Debug, Default)]
+pub struct FreeSpans {
+    by_len: BTreeMap<usize, BTreeSet<usize>>,
+}
+
+impl FreeSpans {
+    pub fn new(spans: impl IntoIterator<Item = Range<usize>>) -> Self {
+        let mut free_spans = Self::default();
+
+        for span in spans {
+            free_spans.insert(span);
+        }
+
+        free_spans
+    }
+
+    fn insert(&mut self, span: Range<usize>) {
+        if !span.is_empty() {
+            self.by_len.entry(span.len()).or_default().insert(span.start);
+        }
+    }
+
+    // Removes the first size blocks of the leftmost span which starts before
+    // end and is at least size long, returning the blocks removed.
+    pub fn take_leftmost(&mut self, size: usize, end: usize) -> Option<Range<usize>> {
+        let (len, start) = self.by_len
+            .range(size.max(1)..)
+            .filter_map(|(len, starts)| Some((*len, *starts.first()?)))
+            .min_by_key(|(_, start)| *start)?;
+
+        if start >= end {
+            return None;
+        }
+
+        let starts = self.by_len.get_mut(&len)?;
+
+        starts.remove(&start);
+
+        if starts.is_empty() {
+            self.by_len.remove(&len);
+        }
+
+        self.insert(start + size..start + len);
+
+        Some(start..start + size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_from_the_leftmost_span_that_fits() {
+        let mut free_spans = FreeSpans::new([2..5, 8..9, 12..16, 20..22]);
+
+        assert_eq!(free_spans.take_leftmost(4, 30), Some(12..16));
+        assert_eq!(free_spans.take_leftmost(4, 30), None);
+        assert_eq!(free_spans.take_leftmost(1, 30), Some(2..3));
+        assert_eq!(free_spans.take_leftmost(2, 30), Some(3..5));
+        assert_eq!(free_spans.take_leftmost(2, 20), None);
+        assert_eq!(free_spans.take_leftmost(1, 30), Some(8..9));
+        assert_eq!(free_spans.take_leftmost(1, 21), Some(20..21));
+        assert_eq!(free_spans.take_leftmost(1, 21), None);
+    }
+
+    #[test]
+    fn handles_huge_spans() {
+        let mut free_spans = FreeSpans::new(Some(0..usize::MAX / 2));
+
+        assert_eq!(free_spans.take_leftmost(usize::MAX / 4, usize::MAX), Some(0..usize::MAX / 4));
+    }
+}
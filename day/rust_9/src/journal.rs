@@ -0,0 +1,225 @@
+use std::{fmt, ops::Range};
+
+use crate::{Disk, DiskBlock};
+
+// Blocks of a file moving from one place on the disk to free space elsewhere.
+// The two ranges are the same length and don't overlap.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Move {
+    pub id: usize,
+    pub from: Range<usize>,
+    pub to: Range<usize>,
+}
+
+impl Move {
+    // The move which puts the blocks back where they came from.
+    pub fn reversed(&self) -> Self {
+        Self {
+            id: self.id,
+            from: self.to.clone(),
+            to: self.from.clone(),
+        }
+    }
+}
+
+// The moves made by a compaction, in the order they were made.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Journal {
+    pub moves: Vec<Move>,
+}
+
+impl Journal {
+    // Records a block by block move, folding it into the previous move when
+    // that took the blocks just after this one to just before this one.
+    pub(crate) fn record_block(&mut self, id: usize, from: usize, to: usize) {
+        if let Some(last) = self.moves.last_mut() {
+            if last.id == id && last.from.start == from + 1 && last.to.end == to {
+                last.from.start = from;
+                last.to.end = to + 1;
+                return;
+            }
+        }
+
+        self.moves.push(Move { id, from: from..from + 1, to: to..to + 1 });
+    }
+
+    // Checks that every move can be made, in order, starting from disk.
+    pub fn verify(&self, disk: &Disk) -> Result<(), JournalError> {
+        disk.clone().replay(self)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MoveError {
+    MismatchedLengths,
+    Overlapping,
+    OutOfBounds,
+    // The block at this position doesn't hold the file being moved.
+    NotFile(usize),
+    // The block at this position isn't free to move the file into.
+    NotFree(usize),
+}
+
+// A move which couldn't be made, and where it is in the journal.
+#[derive(Debug, PartialEq, Eq)]
+pub struct JournalError {
+    pub step: usize,
+    pub kind: MoveError,
+}
+
+impl fmt::Display for JournalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} is invalid: ", self.step)?;
+
+        match self.kind {
+            MoveError::MismatchedLengths => write!(f, "source and destination lengths differ"),
+            MoveError::Overlapping => write!(f, "source and destination overlap"),
+            MoveError::OutOfBounds => write!(f, "it runs off the end of the disk"),
+            MoveError::NotFile(position) => write!(f, "block {} is not the file", position),
+            MoveError::NotFree(position) => write!(f, "block {} is not free", position),
+        }
+    }
+}
+
+impl Disk {
+    pub fn apply(&mut self, file_move: &Move) -> Result<(), MoveError> {
+        let Move { id, from, to } = file_move;
+
+        if from.len() != to.len() {
+            return Err(MoveError::MismatchedLengths);
+        }
+        if from.start < to.end && to.start < from.end {
+            return Err(MoveError::Overlapping);
+        }
+        if from.end.max(to.end) > self.map.len() {
+            return Err(MoveError::OutOfBounds);
+        }
+        if let Some(position) = from.clone().find(|i| self.map[*i] != DiskBlock::File { id: *id }) {
+            return Err(MoveError::NotFile(position));
+        }
+        if let Some(position) = to.clone().find(|i| self.map[*i] != DiskBlock::FreeSpace) {
+            return Err(MoveError::NotFree(position));
+        }
+
+        for (from, to) in from.clone().zip(to.clone()) {
+            self.map.swap(from, to);
+        }
+
+        Ok(())
+    }
+
+    pub fn undo(&mut self, file_move: &Move) -> Result<(), MoveError> {
+        self.apply(&file_move.reversed())
+    }
+
+    // Makes every move in the journal, stopping at the first which can't be
+    // made.
+    pub fn replay(&mut self, journal: &Journal) -> Result<(), JournalError> {
+        for (step, file_move) in journal.moves.iter().enumerate() {
+            self.apply(file_move).map_err(|kind| JournalError { step, kind })?;
+        }
+
+        Ok(())
+    }
+
+    // Undoes every move in the journal, last move first.
+    pub fn rewind(&mut self, journal: &Journal) -> Result<(), JournalError> {
+        for (step, file_move) in journal.moves.iter().enumerate().rev() {
+            self.undo(file_move).map_err(|kind| JournalError { step, kind })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::example_disk;
+
+    #[test]
+    fn journals_part_one_compaction() {
+        let mut disk = example_disk();
+        let journal = disk.compress_journaled();
+
+        assert_eq!(disk.block_string(), "0099811188827773336446555566..............");
+        assert_eq!(journal.moves[0], Move { id: 9, from: 40..42, to: 2..4 });
+        assert_eq!(journal.moves[1], Move { id: 8, from: 39..40, to: 4..5 });
+        assert_eq!(journal.moves.len(), 7);
+
+        let mut replayed = example_disk();
+
+        replayed.replay(&journal).unwrap();
+
+        assert_eq!(replayed.map, disk.map);
+    }
+
+    #[test]
+    fn journals_part_two_compaction() {
+        let mut disk = example_disk();
+        let journal = disk.compress_part_two_journaled();
+
+        assert_eq!(
+            journal.moves,
+            vec![
+                Move { id: 9, from: 40..42, to: 2..4 },
+                Move { id: 7, from: 32..35, to: 8..11 },
+                Move { id: 4, from: 19..21, to: 12..14 },
+                Move { id: 2, from: 11..12, to: 4..5 },
+            ]
+        );
+        assert_eq!(journal.verify(&example_disk()), Ok(()));
+
+        let mut replayed = example_disk();
+
+        replayed.replay(&journal).unwrap();
+
+        assert_eq!(replayed.block_string(), disk.block_string());
+    }
+
+    #[test]
+    fn undoes_step_by_step() {
+        let mut disk = example_disk();
+        let mut journal = disk.compress_part_two_journaled();
+
+        assert_eq!(disk.block_string(), "00992111777.44.333....5555.6666.....8888..");
+
+        let last = journal.moves.pop().unwrap();
+
+        disk.undo(&last).unwrap();
+
+        assert_eq!(disk.block_string(), "0099.111777244.333....5555.6666.....8888..");
+        assert_eq!(disk.undo(&last), Err(MoveError::NotFile(4)));
+
+        disk.rewind(&journal).unwrap();
+
+        assert_eq!(disk.block_string(), "00...111...2...333.44.5555.6666.777.888899");
+    }
+
+    #[test]
+    fn rejects_invalid_journals() {
+        let disk = example_disk();
+        let cases = [
+            (Move { id: 9, from: 40..42, to: 2..5 }, MoveError::MismatchedLengths),
+            (Move { id: 9, from: 40..42, to: 41..43 }, MoveError::Overlapping),
+            (Move { id: 9, from: 40..42, to: 42..44 }, MoveError::OutOfBounds),
+            (Move { id: 8, from: 40..42, to: 2..4 }, MoveError::NotFile(40)),
+            (Move { id: 9, from: 40..42, to: 1..3 }, MoveError::NotFree(1)),
+        ];
+
+        for (file_move, kind) in cases {
+            let journal = Journal {
+                moves: vec![Move { id: 4, from: 19..21, to: 12..14 }, file_move],
+            };
+
+            assert_eq!(journal.verify(&disk), Err(JournalError { step: 1, kind }));
+        }
+
+        let journal = Journal { moves: vec![Move { id: 9, from: 40..42, to: 4..6 }] };
+
+        assert_eq!(
+            journal.verify(&disk).unwrap_err().to_string(),
+            "move 0 is invalid: block 5 is not free"
+        );
+    }
+}
@@ -1,6 +1,21 @@
-use std::{fmt, ops::Range, str::FromStr};
-
-#[derive(Debug)]
+use std::{fmt, io::Read, iter, ops::Range, str::FromStr};
+
+mod allocation;
+mod disk_map;
+mod extent_disk;
+mod free_spans;
+mod journal;
+mod report;
+
+pub use allocation::{AllocationPolicy, Defragmentation};
+use disk_map::parse_runs;
+pub use disk_map::ParseDiskError;
+pub use extent_disk::{Extent, ExtentDisk};
+use free_spans::FreeSpans;
+pub use journal::{Journal, JournalError, Move, MoveError};
+pub use report::DiskReport;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum DiskBlock {
     File { id: usize },
     FreeSpace,
@@ -16,78 +31,107 @@ impl fmt::Display for DiskBlock {
     }
 }
 
+// A checksum too big to fit in 64 bits.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ChecksumOverflow;
+
+impl fmt::Display for ChecksumOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "checksum doesn't fit in 64 bits")
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Disk {
     pub map: Vec<DiskBlock>,
 }
 
 impl Disk {
+    // Only the disk map text is read a chunk at a time; every block still ends
+    // up in the map.
+    pub fn from_reader(reader: impl Read) -> Result<Self, ParseDiskError> {
+        let mut map: Vec<DiskBlock> = Vec::new();
+
+        parse_runs(reader, |block, len| map.extend(iter::repeat_n(block, len)))?;
+
+        Ok(Self {
+            map,
+        })
+    }
+
     pub fn block_string(&self) -> String {
         self.map.iter().map(|b| b.to_string()).collect()
     }
 
-    fn first_free_index(&self, start: usize) -> Option<usize> {
-        let map = &self.map[start..];
+    pub fn disk_map(&self) -> String {
+        ExtentDisk::from(self).disk_map()
+    }
 
-        for (index, block) in map.iter().enumerate() {
-            if let DiskBlock::FreeSpace = block {
-                return Some(start + index);
-            }
-        }
-        None
+    // Moves file blocks one at a time from the end of the disk into the
+    // leftmost free block, until there are no gaps between file blocks. Each
+    // end is only walked once, so this is linear in the size of the map.
+    pub fn compress(&mut self) {
+        self.compress_into(None);
     }
 
-    fn last_occupied_index(&self) -> Option<usize> {
-        for (index, block) in self.map.iter().enumerate().rev() {
-            if let DiskBlock::File { id: _ } = block {
-                return Some(index);
-            }
-        }
-        None
+    // Compresses as above, returning the moves made.
+    pub fn compress_journaled(&mut self) -> Journal {
+        let mut journal = Journal::default();
+
+        self.compress_into(Some(&mut journal));
+        journal
     }
 
-    pub fn compress(&mut self) {
-        let mut last_free_index = 0;
+    fn compress_into(&mut self, mut journal: Option<&mut Journal>) {
+        if self.map.is_empty() {
+            return;
+        }
+
+        let mut free_index = 0;
+        let mut occupied_index = self.map.len() - 1;
 
-        while let Some(free_index) = self.first_free_index(last_free_index) {
-            last_free_index = free_index;
+        loop {
+            while free_index < occupied_index {
+                if let DiskBlock::FreeSpace = self.map[free_index] {
+                    break;
+                }
+                free_index += 1;
+            }
 
-            if let Some(occupied_index) = self.last_occupied_index() {
-                if free_index > occupied_index {
+            while free_index < occupied_index {
+                if let DiskBlock::File { id: _ } = self.map[occupied_index] {
                     break;
                 }
-                self.map.swap(free_index, occupied_index);
-            } else {
+                occupied_index -= 1;
+            }
+
+            if free_index >= occupied_index {
                 break;
             }
+
+            if let Some(journal) = &mut journal {
+                if let DiskBlock::File { id } = self.map[occupied_index] {
+                    journal.record_block(id, occupied_index, free_index);
+                }
+            }
+
+            self.map.swap(free_index, occupied_index);
         }
     }
 
-    fn get_free_block_range(&self, size: usize, end_index: usize) -> Option<Range<usize>> {
-        let mut this_range: Option<Range<usize>> = None;
+    fn free_block_ranges(&self) -> Vec<Range<usize>> {
+        let mut free_block_ranges: Vec<Range<usize>> = Vec::new();
 
-        for (i, block) in self.map[0..end_index].iter().enumerate() {
+        for (i, block) in self.map.iter().enumerate() {
             if let DiskBlock::FreeSpace = block {
-                if let Some(old_range) = this_range {
-                    let new_range = old_range.start..i + 1;
-
-                    if new_range.len() == size {
-                        return Some(new_range);
-                    } else {
-                        this_range = Some(new_range);
-                    }
-                } else {
-                    this_range = Some(i..i + 1);
-
-                    if size == 1 {
-                        return this_range;
-                    }
+                match free_block_ranges.last_mut() {
+                    Some(range) if range.end == i => range.end += 1,
+                    _ => free_block_ranges.push(i..i + 1),
                 }
-            } else if this_range.is_some() {
-                this_range = None;
             }
         }
 
-        None
+        free_block_ranges
     }
 
     fn occupied_block_ranges(&self) -> Vec<Range<usize>> {
@@ -125,73 +169,87 @@ impl Disk {
         occupied_block_ranges
     }
 
+    // Moves whole files, starting from the end of the disk, into the leftmost
+    // free span before them which is big enough. Space freed by a move is
+    // never reused, as every file still to move lies to the left of it.
     pub fn compress_part_two(&mut self) {
+        self.compress_part_two_into(None);
+    }
+
+    // Compresses as above, returning the moves made.
+    pub fn compress_part_two_journaled(&mut self) -> Journal {
+        let mut journal = Journal::default();
+
+        self.compress_part_two_into(Some(&mut journal));
+        journal
+    }
+
+    fn compress_part_two_into(&mut self, mut journal: Option<&mut Journal>) {
         let mut occupied_block_ranges = self.occupied_block_ranges();
+        let mut free_spans = FreeSpans::new(self.free_block_ranges());
 
         occupied_block_ranges.reverse();
 
         for range in occupied_block_ranges {
-            if let Some(free_range) = self.get_free_block_range(range.len(), range.start) {
+            if let Some(free_range) = free_spans.take_leftmost(range.len(), range.start) {
+                if let Some(journal) = &mut journal {
+                    if let DiskBlock::File { id } = self.map[range.start] {
+                        let to = free_range.clone();
+
+                        journal.moves.push(Move { id, from: range.clone(), to });
+                    }
+                }
+
                 for (i, free_i) in free_range.enumerate() {
                     self.map.swap(free_i, range.start + i);
                 }
             }
         }
+    }
 
+    pub fn checksum(&self) -> Result<u64, ChecksumOverflow> {
+        self.checked_checksum().ok_or(ChecksumOverflow)
     }
 
-    pub fn checksum(&self) -> u64 {
+    // Sums position times id over every file block in 64-bit arithmetic,
+    // returning None if the total doesn't fit.
+    fn checked_checksum(&self) -> Option<u64> {
         let mut total: u64 = 0;
 
         for (i, block) in self.map.iter().enumerate() {
             if let DiskBlock::File { id } = block {
-                let id_digit = u32::try_from(id.clone()).unwrap();
-                let position = u32::try_from(i).unwrap();
+                let id = u64::try_from(*id).ok()?;
+                let position = u64::try_from(i).ok()?;
 
-                total = total + u64::try_from(position * id_digit).unwrap();
+                total = total.checked_add(position.checked_mul(id)?)?;
             }
         }
 
-        total
+        Some(total)
     }
 }
 
-#[derive(Debug)]
-pub struct ParseDiskError;
-
 impl FromStr for Disk {
     type Err = ParseDiskError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let chars: Vec<char> = s.chars().collect();
-        let mut map: Vec<DiskBlock> = Vec::new();
-
-        for (id, chunk) in chars.chunks(2).enumerate() {
-            let blocks = chunk[0].to_digit(10).unwrap();
-
-            for _ in 0..blocks {
-                map.push(DiskBlock::File { id });
-            }
-
-            if let Some(e) = chunk.get(1) {
-                let empties = e.to_digit(10).unwrap();
-
-                for _ in 0..empties {
-                    map.push(DiskBlock::FreeSpace);
-                }
-            }
-        }
-
-        Ok(Self {
-            map,
-        })
+        Self::from_reader(s.as_bytes())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io;
+
     use super::*;
 
+    // The example from the puzzle, which the tests of every module start from.
+    pub(crate) const DISK_MAP: &str = "2333133121414131402";
+
+    pub(crate) fn example_disk() -> Disk {
+        Disk::from_str(DISK_MAP).unwrap()
+    }
+
     #[test]
     fn parses_into_a_block_string() {
         assert_eq!(
@@ -200,6 +258,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parses_from_a_reader_and_ignores_trailing_whitespace() {
+        let disk = Disk::from_reader("2333133121414131402\n".as_bytes()).unwrap();
+
+        assert_eq!(disk.block_string(), "00...111...2...333.44.5555.6666.777.888899");
+
+        let disk = Disk::from_reader(io::repeat(b'1').take(1_000_001)).unwrap();
+
+        assert_eq!(disk.map.len(), 1_000_001);
+        assert_eq!(disk.map[1_000_000], DiskBlock::File { id: 500_000 });
+    }
+
+    #[test]
+    fn errors_on_invalid_characters() {
+        assert!(matches!(
+            Disk::from_str("2333 1331"),
+            Err(ParseDiskError::InvalidCharacter { offset: 4, found: b' ' })
+        ));
+        assert!(matches!(
+            ExtentDisk::from_str("23-3"),
+            Err(ParseDiskError::InvalidCharacter { offset: 2, found: b'-' })
+        ));
+    }
+
+    #[test]
+    fn renders_compressed_disks_as_disk_maps_that_round_trip() {
+        let mut disk = Disk::from_str("2333133121414131402").unwrap();
+
+        disk.compress_part_two();
+
+        let disk_map = disk.disk_map();
+
+        assert_eq!(disk_map, "20[9]20[2]10[1]30[7]31[4]21[3]34[5]4145[8]42");
+
+        let parsed = Disk::from_str(&disk_map).unwrap();
+
+        assert_eq!(parsed.block_string(), disk.block_string());
+        assert_eq!(parsed.disk_map(), disk_map);
+    }
+
     #[test]
     fn compresses() {
         let mut disk = Disk::from_str("2333133121414131402").unwrap();
@@ -224,6 +322,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn compresses_edge_cases() {
+        let cases = [
+            ("", ""),
+            ("0", ""),
+            ("1", "0"),
+            ("10101", "012"),
+            ("0310", "1..."),
+            ("12345", "022111222......"),
+        ];
+
+        for (disk_map, expected) in cases {
+            let mut disk = Disk::from_str(disk_map).unwrap();
+
+            disk.compress();
+
+            assert_eq!(disk.block_string(), expected, "{}", disk_map);
+        }
+    }
+
+    #[test]
+    fn moves_whole_files_into_the_leftmost_span_that_fits() {
+        let cases = [
+            ("12345", "0..111....22222"),
+            ("1313165", "021......33333......"),
+            ("2030201", "00111223"),
+            ("14113", "02221....."),
+        ];
+
+        for (disk_map, expected) in cases {
+            let mut disk = Disk::from_str(disk_map).unwrap();
+
+            disk.compress_part_two();
+
+            assert_eq!(disk.block_string(), expected, "{}", disk_map);
+        }
+    }
+
     #[test]
     fn can_calculate_a_checksum() {
         let mut disk = Disk::from_str("2333133121414131402").unwrap();
@@ -232,7 +368,7 @@ mod tests {
 
         assert_eq!(
             disk.checksum(),
-            1928
+            Ok(1928)
         );
 
         let mut disk = Disk::from_str("2333133121414131402").unwrap();
@@ -241,7 +377,48 @@ mod tests {
 
         assert_eq!(
             disk.checksum(),
-            2858
+            Ok(2858)
         );
     }
+
+    #[test]
+    fn checksums_millions_of_blocks() {
+        let n: u128 = 3_000_000;
+        let disk = Disk {
+            map: (0..n as usize).map(|id| DiskBlock::File { id }).collect(),
+        };
+
+        // The sum of i * i for i below n.
+        assert_eq!(u128::from(disk.checksum().unwrap()), (n - 1) * n * (2 * n - 1) / 6);
+
+        let mut disk = Disk::from_str(&"91".repeat(200_000)).unwrap();
+        let mut extent_disk = ExtentDisk::from(&disk);
+
+        disk.compress();
+        extent_disk.compress();
+
+        assert_eq!(disk.map.len(), 2_000_000);
+        assert_eq!(disk.checksum(), extent_disk.checksum());
+        assert_eq!(disk.checksum(), Ok(205_199_181_000_450_000));
+    }
+
+    #[test]
+    fn reports_a_checksum_that_overflows() {
+        let disk = Disk {
+            map: vec![DiskBlock::FreeSpace, DiskBlock::File { id: usize::MAX / 2 + 1 }],
+        };
+
+        assert_eq!(disk.checksum(), Ok(u64::MAX / 2 + 1));
+
+        let disk = Disk {
+            map: vec![
+                DiskBlock::FreeSpace,
+                DiskBlock::FreeSpace,
+                DiskBlock::File { id: usize::MAX / 2 + 1 },
+            ],
+        };
+
+        assert_eq!(disk.checksum(), Err(ChecksumOverflow));
+        assert_eq!(disk.checksum().unwrap_err().to_string(), "checksum doesn't fit in 64 bits");
+    }
 }
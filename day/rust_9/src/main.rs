@@ -1,18 +1,44 @@
 use std::str::FromStr;
-use rust_9::Disk;
+use rust_9::{AllocationPolicy, ChecksumOverflow, Disk};
+
+// A checksum, or why there isn't one.
+fn describe(checksum: Result<u64, ChecksumOverflow>) -> String {
+    match checksum {
+        Ok(checksum) => checksum.to_string(),
+        Err(err) => err.to_string(),
+    }
+}
 
 fn main() {
     let file = include_str!("../input");
 
     for line in file.lines() {
-        let mut disk = Disk::from_str(&line).unwrap();
+        let mut disk = Disk::from_str(line).unwrap();
+
+        println!("before: {}", disk.report());
 
         disk.compress();
-        println!("part 1 solution: {}", disk.checksum());
+        println!("part 1 solution: {}", describe(disk.checksum()));
+        println!("after part 1: {}", disk.report());
 
-        let mut disk = Disk::from_str(&line).unwrap();
+        let mut disk = Disk::from_str(line).unwrap();
 
         disk.compress_part_two();
-        println!("part 2 solution: {}", disk.checksum());
+        println!("part 2 solution: {}", describe(disk.checksum()));
+        println!("after part 2: {}", disk.report());
+
+        for policy in AllocationPolicy::ALL {
+            let mut disk = Disk::from_str(line).unwrap();
+            let defragmentation = disk.defragment(&policy);
+
+            println!(
+                "{:?}: checksum {}, {} moves, {} gaps of {} blocks",
+                policy,
+                describe(defragmentation.checksum),
+                defragmentation.moves,
+                defragmentation.gaps,
+                defragmentation.gap_blocks
+            );
+        }
     }
 }
@@ -0,0 +1,140 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{Disk, DiskBlock, ExtentDisk};
+
+// How fragmented the files and free space on a disk are.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct DiskReport {
+    // The number of separate runs of blocks each file id is stored in.
+    pub fragments: BTreeMap<usize, usize>,
+    pub free_blocks: usize,
+    pub largest_free_span: usize,
+    // How many free spans there are of each length.
+    pub free_span_histogram: BTreeMap<usize, usize>,
+    pub last_used_block: Option<usize>,
+}
+
+impl DiskReport {
+    pub fn files(&self) -> usize {
+        self.fragments.len()
+    }
+
+    pub fn contiguous_files(&self) -> usize {
+        self.fragments.values().filter(|fragments| **fragments == 1).count()
+    }
+
+    // The share of files stored in one piece, which is all of them when there
+    // are none.
+    pub fn contiguous_share(&self) -> f64 {
+        if self.files() == 0 {
+            1.0
+        } else {
+            self.contiguous_files() as f64 / self.files() as f64
+        }
+    }
+}
+
+impl fmt::Display for DiskReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} files ({:.1}% contiguous), {} free blocks in {} spans (largest {})",
+            self.files(),
+            self.contiguous_share() * 100.0,
+            self.free_blocks,
+            self.free_span_histogram.values().sum::<usize>(),
+            self.largest_free_span
+        )?;
+
+        match self.last_used_block {
+            Some(last_used_block) => write!(f, ", last used block {}", last_used_block),
+            None => write!(f, ", no used blocks"),
+        }
+    }
+}
+
+impl Disk {
+    pub fn report(&self) -> DiskReport {
+        let mut report = DiskReport::default();
+
+        for extent in ExtentDisk::from(self).extents {
+            match extent.block {
+                DiskBlock::File { id } => {
+                    *report.fragments.entry(id).or_default() += 1;
+                    report.last_used_block = Some(extent.end() - 1);
+                },
+                DiskBlock::FreeSpace => {
+                    *report.free_span_histogram.entry(extent.len).or_default() += 1;
+                    report.free_blocks += extent.len;
+                    report.largest_free_span = report.largest_free_span.max(extent.len);
+                },
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::tests::example_disk;
+
+    #[test]
+    fn reports_on_a_fresh_disk() {
+        let report = example_disk().report();
+
+        assert_eq!(report.files(), 10);
+        assert_eq!(report.contiguous_share(), 1.0);
+        assert_eq!(report.free_blocks, 14);
+        assert_eq!(report.largest_free_span, 3);
+        assert_eq!(report.free_span_histogram, BTreeMap::from([(1, 5), (3, 3)]));
+        assert_eq!(report.last_used_block, Some(41));
+    }
+
+    #[test]
+    fn reports_on_compressed_disks() {
+        let mut disk = example_disk();
+
+        disk.compress();
+
+        let report = disk.report();
+
+        // 00 99 8 111 888 2 777 333 6 44 6 5555 66
+        assert_eq!(report.fragments[&6], 3);
+        assert_eq!(report.fragments[&8], 2);
+        assert_eq!(report.contiguous_files(), 8);
+        assert_eq!(report.contiguous_share(), 0.8);
+        assert_eq!(report.free_span_histogram, BTreeMap::from([(14, 1)]));
+        assert_eq!(report.last_used_block, Some(27));
+
+        let mut disk = example_disk();
+
+        disk.compress_part_two();
+
+        let report = disk.report();
+
+        assert_eq!(report.contiguous_share(), 1.0);
+        assert_eq!(report.largest_free_span, 5);
+        assert_eq!(report.free_span_histogram, BTreeMap::from([(1, 3), (2, 1), (4, 1), (5, 1)]));
+        assert_eq!(report.last_used_block, Some(39));
+        assert_eq!(
+            report.to_string(),
+            "10 files (100.0% contiguous), 14 free blocks in 6 spans (largest 5), \
+             last used block 39"
+        );
+    }
+
+    #[test]
+    fn reports_on_an_empty_disk() {
+        let report = Disk::from_str("").unwrap().report();
+
+        assert_eq!(report, DiskReport::default());
+        assert_eq!(
+            report.to_string(),
+            "0 files (100.0% contiguous), 0 free blocks in 0 spans (largest 0), no used blocks"
+        );
+    }
+}